@@ -0,0 +1,877 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    behavior::BehaviorRunner,
+    character::{HealthComponent, NameComponent},
+    combat::{Attacking, MeleeAttack},
+    enemy::EnemyTag,
+    health::Dead,
+    movable::{HitReaction, Movable},
+    navigation::NavPath,
+    perception::{Perception, PerceptionSet},
+    states::{gameplay_running, GameState, StateScoped},
+    steering::{self, Wander},
+};
+
+// Ring the pack surrounds the player on, pack members with a melee attack close in to reach
+const PACK_RADIUS: f32 = 6.0;
+// How far from home patrols wander
+const PATROL_RADIUS: f32 = 8.0;
+// Distance at which an investigated position counts as reached
+const ARRIVE_DISTANCE: f32 = 1.0;
+// Attacks only start once the enemy looks at the player this closely, in radians
+pub const ATTACK_ANGLE: f32 = 0.5;
+// How far a fleeing enemy tries to get from the player
+const FLEE_DISTANCE: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiState {
+    Idle,
+    // Wander around home
+    Patrol,
+    // Go where the player was last seen
+    Investigate,
+    Chase,
+    Attack,
+    Flee,
+    Dead,
+}
+
+impl AiState {
+    // The one place states are dispatched from, a new state only adds its hooks here
+    fn hooks(self) -> &'static StateHooks {
+        match self {
+            AiState::Idle => &IDLE,
+            AiState::Patrol => &PATROL,
+            AiState::Investigate => &INVESTIGATE,
+            AiState::Chase => &CHASE,
+            AiState::Attack => &ATTACK,
+            AiState::Flee => &FLEE,
+            AiState::Dead => &DEAD,
+        }
+    }
+}
+
+// Everything a state does, see the hooks of each state below
+struct StateHooks {
+    // Seconds the state lasts at most, its timer is reset on enter
+    duration: Option<f32>,
+    // State to switch to, if any. Death and fleeing are checked before, by `transition`
+    transition: fn(&EnemyAi, &Senses, Option<&MeleeAttack>) -> Option<AiState>,
+    on_enter: fn(&mut StateContext),
+    // Every frame the enemy is free to act, not while reacting to a hit or attacking
+    update: fn(&mut StateContext),
+    on_exit: fn(&mut StateContext),
+}
+
+const DEFAULT_HOOKS: StateHooks = StateHooks {
+    duration: None,
+    transition: |_, _, _| None,
+    on_enter: |_| {},
+    update: |_| {},
+    on_exit: stop,
+};
+
+// Tuning of an enemy's AI, from the `ai` section of its manifest
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct AiConfig {
+    // Flees once (for the state's duration) under this fraction of its health
+    pub flee_health: Option<f32>,
+    // Surround the player with the other pack members, each one taking its own slot
+    pub pack: bool,
+    // Runs instead of walking once the target is this close
+    pub run_distance: f32,
+    // Where enemies without a melee attack stop in front of the player
+    pub keep_distance: f32,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        Self {
+            flee_health: None,
+            pack: false,
+            run_distance: 18.0,
+            keep_distance: 7.0,
+        }
+    }
+}
+
+// State machine driving an enemy, see `transition` for when states change
+#[derive(Component, Debug)]
+pub struct EnemyAi {
+    pub config: AiConfig,
+    state: AiState,
+    // Seconds spent in the current state
+    elapsed: f32,
+    // Counts down the state's duration, if it has one
+    timer: Option<Timer>,
+    // Patrols happen around the spawn point
+    home: Vec3,
+    wander: Wander,
+    fled: bool,
+}
+
+impl EnemyAi {
+    pub fn new(home: Vec3, config: AiConfig) -> Self {
+        let mut ai = Self {
+            config,
+            state: AiState::Idle,
+            elapsed: 0.0,
+            timer: None,
+            home,
+            wander: Wander::new(home.x.to_bits() ^ home.z.to_bits().rotate_left(16)),
+            fled: false,
+        };
+        ai.reset_timer();
+        ai
+    }
+
+    fn reset_timer(&mut self) {
+        self.elapsed = 0.0;
+        self.timer = self
+            .state
+            .hooks()
+            .duration
+            .map(|seconds| Timer::from_seconds(seconds, TimerMode::Once));
+    }
+
+    fn timed_out(&self) -> bool {
+        self.timer.as_ref().is_some_and(Timer::finished)
+    }
+}
+
+// What an enemy knows this frame
+struct Senses {
+    position: Vec3,
+    // The player, when in view
+    player: Option<Vec3>,
+    hears_player: bool,
+    last_known_position: Option<Vec3>,
+    player_velocity: Vec3,
+    health_fraction: f32,
+}
+
+impl Senses {
+    fn new(position: Vec3, perception: &Perception, health: &HealthComponent) -> Self {
+        Self {
+            position,
+            player: perception.visible_player(),
+            hears_player: perception.hears_player,
+            last_known_position: perception.last_known_position,
+            player_velocity: perception.player_velocity,
+            health_fraction: health.current / health.max,
+        }
+    }
+
+    fn player_distance(&self) -> f32 {
+        self.player
+            .map_or(f32::INFINITY, |player| self.position.distance(player))
+    }
+}
+
+// What the hooks of a state work with, for one enemy
+struct StateContext<'a> {
+    ai: &'a mut EnemyAi,
+    movable: &'a mut Movable,
+    transform: &'a Transform,
+    nav_path: &'a mut NavPath,
+    senses: &'a Senses,
+    melee_attack: Option<&'a MeleeAttack>,
+    // Slot around the player and the number of pack members chasing it
+    pack_slot: Option<(usize, usize)>,
+    delta_seconds: f32,
+    // Applied once the update is over
+    start_attack: bool,
+}
+
+// Tells the state to switch to, if any
+fn transition(
+    ai: &EnemyAi,
+    senses: &Senses,
+    melee_attack: Option<&MeleeAttack>,
+) -> Option<AiState> {
+    if ai.state == AiState::Dead {
+        return None;
+    }
+    if ai.state != AiState::Flee
+        && !ai.fled
+        && ai
+            .config
+            .flee_health
+            .is_some_and(|flee_health| senses.health_fraction < flee_health)
+    {
+        return Some(AiState::Flee);
+    }
+    (ai.state.hooks().transition)(ai, senses, melee_attack)
+}
+
+fn change_state(context: &mut StateContext, state: AiState) {
+    (context.ai.state.hooks().on_exit)(context);
+    context.ai.state = state;
+    context.ai.reset_timer();
+    (state.hooks().on_enter)(context);
+}
+
+// Leaving a state stops whatever it was doing, the next one starts from rest
+fn stop(context: &mut StateContext) {
+    context.movable.throttle = 0.0;
+    context.movable.heading = None;
+    context.movable.fast = false;
+    context.nav_path.clear();
+}
+
+// Stand around home for a moment
+const IDLE: StateHooks = StateHooks {
+    duration: Some(2.0),
+    transition: idle_transition,
+    ..DEFAULT_HOOKS
+};
+
+fn idle_transition(ai: &EnemyAi, senses: &Senses, _: Option<&MeleeAttack>) -> Option<AiState> {
+    if senses.player.is_some() {
+        Some(AiState::Chase)
+    } else if senses.hears_player {
+        Some(AiState::Investigate)
+    } else {
+        ai.timed_out().then_some(AiState::Patrol)
+    }
+}
+
+const PATROL: StateHooks = StateHooks {
+    duration: Some(10.0),
+    transition: patrol_transition,
+    update: patrol_update,
+    ..DEFAULT_HOOKS
+};
+
+fn patrol_transition(ai: &EnemyAi, senses: &Senses, _: Option<&MeleeAttack>) -> Option<AiState> {
+    if senses.player.is_some() {
+        Some(AiState::Chase)
+    } else if senses.hears_player {
+        Some(AiState::Investigate)
+    } else {
+        ai.timed_out().then_some(AiState::Idle)
+    }
+}
+
+fn patrol_update(context: &mut StateContext) {
+    let home = context.ai.home;
+    let steering = steering::wander(
+        context.transform,
+        &mut context.ai.wander,
+        home,
+        PATROL_RADIUS,
+        context.delta_seconds,
+    );
+    steering::apply(context.movable, context.transform, steering);
+}
+
+const INVESTIGATE: StateHooks = StateHooks {
+    duration: Some(6.0),
+    transition: investigate_transition,
+    update: investigate_update,
+    ..DEFAULT_HOOKS
+};
+
+// Search until the last known position is reached or forgotten
+fn investigate_transition(
+    ai: &EnemyAi,
+    senses: &Senses,
+    _: Option<&MeleeAttack>,
+) -> Option<AiState> {
+    if senses.player.is_some() {
+        return Some(AiState::Chase);
+    }
+    let arrived = senses.last_known_position.map_or(true, |position| {
+        senses.position.distance(position) <= ARRIVE_DISTANCE
+    });
+    (arrived || ai.timed_out()).then_some(AiState::Patrol)
+}
+
+fn investigate_update(context: &mut StateContext) {
+    let Some(last_known_position) = context.senses.last_known_position else {
+        stop(context);
+        return;
+    };
+    chase(
+        context.movable,
+        context.transform,
+        context.nav_path,
+        last_known_position,
+        ARRIVE_DISTANCE,
+        // No hurry when nothing is in sight
+        0.0,
+    );
+}
+
+const CHASE: StateHooks = StateHooks {
+    transition: chase_transition,
+    update: chase_update,
+    ..DEFAULT_HOOKS
+};
+
+fn chase_transition(
+    _: &EnemyAi,
+    senses: &Senses,
+    melee_attack: Option<&MeleeAttack>,
+) -> Option<AiState> {
+    if senses.player.is_none() {
+        return Some(AiState::Investigate);
+    }
+    melee_attack
+        .is_some_and(|melee_attack| senses.player_distance() <= melee_attack.stats.range)
+        .then_some(AiState::Attack)
+}
+
+fn chase_update(context: &mut StateContext) {
+    let Some(player) = context.senses.last_known_position else {
+        stop(context);
+        return;
+    };
+    let config = context.ai.config;
+    if let Some((slot, pack_size)) = context.pack_slot {
+        let angle = slot as f32 / pack_size as f32 * TAU;
+        let radius = context
+            .melee_attack
+            .map_or(PACK_RADIUS, |melee_attack| melee_attack.stats.range * 0.6);
+        let slot = player + Quat::from_rotation_y(angle) * Vec3::Z * radius;
+        chase(
+            context.movable,
+            context.transform,
+            context.nav_path,
+            slot,
+            // Stays within the attack range once at the slot
+            radius * 0.5,
+            config.run_distance,
+        );
+        return;
+    }
+
+    // Head for where the player is going, not where it is
+    let target = steering::pursue(
+        context.senses.position,
+        player,
+        context.senses.player_velocity,
+        context.movable.max_speed,
+    );
+    // Get a bit closer than the range so small moves don't break it off
+    let stop_distance = context
+        .melee_attack
+        .map_or(config.keep_distance, |melee_attack| {
+            melee_attack.stats.range * 0.8
+        });
+    chase(
+        context.movable,
+        context.transform,
+        context.nav_path,
+        target,
+        stop_distance,
+        config.run_distance,
+    );
+}
+
+const ATTACK: StateHooks = StateHooks {
+    transition: attack_transition,
+    update: attack_update,
+    ..DEFAULT_HOOKS
+};
+
+fn attack_transition(
+    _: &EnemyAi,
+    senses: &Senses,
+    melee_attack: Option<&MeleeAttack>,
+) -> Option<AiState> {
+    if senses.player.is_none() {
+        return Some(AiState::Investigate);
+    }
+    melee_attack
+        .map_or(true, |melee_attack| {
+            senses.player_distance() > melee_attack.stats.range
+        })
+        .then_some(AiState::Chase)
+}
+
+fn attack_update(context: &mut StateContext) {
+    let Some(player) = context.senses.player else {
+        stop(context);
+        return;
+    };
+    steering::apply(
+        context.movable,
+        context.transform,
+        steering::face(context.senses.position, player),
+    );
+    context.start_attack = context.melee_attack.is_some_and(|melee_attack| {
+        melee_attack.is_ready() && steering::is_facing(context.transform, player, ATTACK_ANGLE)
+    });
+}
+
+// Run away from the player, once, for the state's duration
+const FLEE: StateHooks = StateHooks {
+    duration: Some(3.0),
+    transition: flee_transition,
+    on_enter: flee_enter,
+    update: flee_update,
+    ..DEFAULT_HOOKS
+};
+
+fn flee_transition(ai: &EnemyAi, senses: &Senses, _: Option<&MeleeAttack>) -> Option<AiState> {
+    ai.timed_out().then_some(if senses.player.is_some() {
+        AiState::Chase
+    } else {
+        AiState::Idle
+    })
+}
+
+fn flee_enter(context: &mut StateContext) {
+    context.ai.fled = true;
+}
+
+fn flee_update(context: &mut StateContext) {
+    let Some(player) = context.senses.last_known_position else {
+        stop(context);
+        return;
+    };
+    let position = context.senses.position;
+    let away = steering::flee(position, player).direction;
+    chase(
+        context.movable,
+        context.transform,
+        context.nav_path,
+        position + away * FLEE_DISTANCE,
+        ARRIVE_DISTANCE,
+        f32::INFINITY,
+    );
+}
+
+// Never left, the death animation is played by the movable
+const DEAD: StateHooks = StateHooks {
+    on_enter: stop,
+    ..DEFAULT_HOOKS
+};
+
+#[derive(Component)]
+struct AiDebugText;
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), spawn_ai_debug_text)
+            .add_systems(
+                Update,
+                (run_ai, toggle_ai_debug_text, update_ai_debug_text)
+                    .chain()
+                    .after(PerceptionSet)
+                    .run_if(gameplay_running),
+            );
+    }
+}
+
+fn run_ai(
+    mut commands: Commands,
+    mut enemies: Query<
+        (
+            Entity,
+            &Transform,
+            &mut EnemyAi,
+            &mut Movable,
+            &mut NavPath,
+            &HealthComponent,
+            &Perception,
+            Option<&MeleeAttack>,
+            Has<Dead>,
+            Has<HitReaction>,
+            Has<Attacking>,
+        ),
+        With<EnemyTag>,
+    >,
+    time: Res<Time>,
+) {
+    let pack_size = enemies
+        .iter()
+        .filter(|(_, _, ai, ..)| ai.config.pack && ai.state == AiState::Chase)
+        .count();
+    let mut pack_slot = 0;
+
+    for (
+        entity,
+        transform,
+        mut ai,
+        mut movable,
+        mut nav_path,
+        health,
+        perception,
+        melee_attack,
+        dead,
+        hit_reaction,
+        attacking,
+    ) in enemies.iter_mut()
+    {
+        let senses = Senses::new(transform.translation, perception, health);
+        ai.elapsed += time.delta_seconds();
+        if let Some(timer) = ai.timer.as_mut() {
+            timer.tick(time.delta());
+        }
+
+        let next = if dead {
+            (ai.state != AiState::Dead).then_some(AiState::Dead)
+        } else if hit_reaction || attacking {
+            // Let the reaction or attack play out first
+            None
+        } else {
+            transition(&ai, &senses, melee_attack)
+        };
+
+        let mut context = StateContext {
+            ai: &mut ai,
+            movable: &mut movable,
+            transform,
+            nav_path: &mut nav_path,
+            senses: &senses,
+            melee_attack,
+            pack_slot: None,
+            delta_seconds: time.delta_seconds(),
+            start_attack: false,
+        };
+        if let Some(next) = next {
+            change_state(&mut context, next);
+        }
+        if dead || hit_reaction || attacking {
+            continue;
+        }
+
+        let state = context.ai.state;
+        if context.ai.config.pack && state == AiState::Chase {
+            context.pack_slot = Some((pack_slot, pack_size.max(1)));
+            pack_slot += 1;
+        }
+        (state.hooks().update)(&mut context);
+        if context.start_attack {
+            if let Some(melee_attack) = melee_attack {
+                commands.entity(entity).insert(melee_attack.attacking());
+            }
+        }
+    }
+}
+
+// Follow the navmesh path to the target and walk, run or stop depending on how far it is.
+// Waypoints are passed at full speed, only the last stretch slows down
+pub fn chase(
+    movable: &mut Movable,
+    transform: &Transform,
+    nav_path: &mut NavPath,
+    target: Vec3,
+    stop_distance: f32,
+    run_distance: f32,
+) {
+    let position = transform.translation;
+    let distance = position.distance(target);
+
+    if distance > stop_distance {
+        let waypoint = nav_path.steer(position, target);
+        let steering = if waypoint == target {
+            steering::arrive(position, target, stop_distance)
+        } else {
+            steering::seek(position, waypoint)
+        };
+        steering::apply(movable, transform, steering);
+        // Walk up from afar, run in for the last stretch
+        movable.fast = distance <= run_distance;
+    } else {
+        steering::apply(movable, transform, steering::face(position, target));
+        nav_path.clear();
+    }
+}
+
+// F3 shows the state of every enemy in the top left corner
+fn spawn_ai_debug_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        Visibility::Hidden,
+        AiDebugText,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+fn toggle_ai_debug_text(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut texts: Query<&mut Visibility, With<AiDebugText>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F3) {
+        return;
+    }
+    for mut visibility in texts.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_ai_debug_text(
+    mut texts: Query<(&mut Text, &Visibility), With<AiDebugText>>,
+    enemies: Query<(&NameComponent, &EnemyAi, &Perception)>,
+    tree_enemies: Query<(&NameComponent, &BehaviorRunner, &Perception)>,
+) {
+    for (mut text, visibility) in texts.iter_mut() {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        text.sections[0].value = enemies
+            .iter()
+            .map(|(name, ai, perception)| {
+                format!(
+                    "{}: {:?} ({:.1}s){}",
+                    name.0,
+                    ai.state,
+                    ai.elapsed,
+                    awareness(perception)
+                )
+            })
+            .chain(tree_enemies.iter().map(|(name, runner, perception)| {
+                format!(
+                    "{}: tree, {}{}",
+                    name.0,
+                    runner.active,
+                    awareness(perception)
+                )
+            }))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+fn awareness(perception: &Perception) -> &'static str {
+    if perception.sees_player {
+        ", sees the player"
+    } else if perception.hears_player {
+        ", hears the player"
+    } else if perception.last_known_position.is_some() {
+        ", searching"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combat::{MeleeHit, MeleeStats};
+
+    use super::*;
+
+    fn ai(state: AiState) -> EnemyAi {
+        let mut ai = EnemyAi::new(Vec3::ZERO, AiConfig::default());
+        ai.state = state;
+        ai.reset_timer();
+        ai
+    }
+
+    fn time_out(ai: &mut EnemyAi) {
+        let timer = ai.timer.as_mut().unwrap();
+        let duration = timer.duration();
+        timer.tick(duration);
+    }
+
+    fn senses() -> Senses {
+        Senses {
+            position: Vec3::ZERO,
+            player: None,
+            hears_player: false,
+            last_known_position: None,
+            player_velocity: Vec3::ZERO,
+            health_fraction: 1.0,
+        }
+    }
+
+    fn sees_player_at(distance: f32) -> Senses {
+        let player = Vec3::new(0.0, 0.0, distance);
+        Senses {
+            player: Some(player),
+            last_known_position: Some(player),
+            ..senses()
+        }
+    }
+
+    fn melee_attack() -> MeleeAttack {
+        MeleeAttack::new(
+            MeleeStats {
+                range: 2.0,
+                cooldown: 1.0,
+                hit: MeleeHit {
+                    damage: 10.0,
+                    hit_time: 0.5,
+                    hit_radius: 1.0,
+                    hit_distance: 1.0,
+                    knockback: 0.0,
+                    hit_stop: 0.0,
+                },
+            },
+            Handle::default(),
+        )
+    }
+
+    #[test]
+    fn idle_patrols_once_timed_out() {
+        let mut idle = ai(AiState::Idle);
+        assert_eq!(transition(&idle, &senses(), None), None);
+        time_out(&mut idle);
+        assert_eq!(transition(&idle, &senses(), None), Some(AiState::Patrol));
+    }
+
+    #[test]
+    fn seeing_the_player_wins_over_hearing_it() {
+        let hears = Senses {
+            hears_player: true,
+            last_known_position: Some(Vec3::X),
+            ..senses()
+        };
+        let sees_and_hears = Senses {
+            hears_player: true,
+            ..sees_player_at(10.0)
+        };
+        for state in [AiState::Idle, AiState::Patrol] {
+            assert_eq!(
+                transition(&ai(state), &hears, None),
+                Some(AiState::Investigate)
+            );
+            assert_eq!(
+                transition(&ai(state), &sees_and_hears, None),
+                Some(AiState::Chase)
+            );
+        }
+    }
+
+    #[test]
+    fn investigating_ends_at_the_last_known_position() {
+        let investigate = ai(AiState::Investigate);
+        let far = Senses {
+            last_known_position: Some(Vec3::new(0.0, 0.0, 10.0)),
+            ..senses()
+        };
+        let arrived = Senses {
+            last_known_position: Some(Vec3::new(0.0, 0.0, 0.5)),
+            ..senses()
+        };
+        assert_eq!(transition(&investigate, &far, None), None);
+        assert_eq!(
+            transition(&investigate, &arrived, None),
+            Some(AiState::Patrol)
+        );
+        // Forgotten
+        assert_eq!(
+            transition(&investigate, &senses(), None),
+            Some(AiState::Patrol)
+        );
+    }
+
+    #[test]
+    fn attacks_within_melee_range() {
+        let melee_attack = melee_attack();
+        assert_eq!(
+            transition(
+                &ai(AiState::Chase),
+                &sees_player_at(1.5),
+                Some(&melee_attack)
+            ),
+            Some(AiState::Attack)
+        );
+        assert_eq!(
+            transition(
+                &ai(AiState::Chase),
+                &sees_player_at(5.0),
+                Some(&melee_attack)
+            ),
+            None
+        );
+        assert_eq!(
+            transition(
+                &ai(AiState::Attack),
+                &sees_player_at(5.0),
+                Some(&melee_attack)
+            ),
+            Some(AiState::Chase)
+        );
+        // Nothing to attack with
+        assert_eq!(
+            transition(&ai(AiState::Chase), &sees_player_at(1.5), None),
+            None
+        );
+    }
+
+    #[test]
+    fn losing_sight_investigates() {
+        let searching = Senses {
+            last_known_position: Some(Vec3::X),
+            ..senses()
+        };
+        for state in [AiState::Chase, AiState::Attack] {
+            assert_eq!(
+                transition(&ai(state), &searching, None),
+                Some(AiState::Investigate)
+            );
+        }
+    }
+
+    #[test]
+    fn flees_only_once() {
+        let config = AiConfig {
+            flee_health: Some(0.3),
+            ..default()
+        };
+        let hurt = Senses {
+            health_fraction: 0.2,
+            ..sees_player_at(10.0)
+        };
+        let mut chase = EnemyAi::new(Vec3::ZERO, config);
+        chase.state = AiState::Chase;
+        assert_eq!(transition(&chase, &hurt, None), Some(AiState::Flee));
+
+        let mut flee = EnemyAi::new(Vec3::ZERO, config);
+        flee.state = AiState::Flee;
+        flee.fled = true;
+        flee.reset_timer();
+        assert_eq!(transition(&flee, &hurt, None), None);
+        time_out(&mut flee);
+        assert_eq!(transition(&flee, &hurt, None), Some(AiState::Chase));
+
+        flee.state = AiState::Chase;
+        assert_eq!(transition(&flee, &hurt, None), None);
+    }
+
+    #[test]
+    fn the_dead_stay_dead() {
+        let hurt = Senses {
+            health_fraction: 0.0,
+            ..sees_player_at(1.0)
+        };
+        let dead = EnemyAi {
+            state: AiState::Dead,
+            ..EnemyAi::new(
+                Vec3::ZERO,
+                AiConfig {
+                    flee_health: Some(0.3),
+                    ..default()
+                },
+            )
+        };
+        assert_eq!(transition(&dead, &hurt, None), None);
+    }
+}
@@ -1,114 +1,635 @@
-use bevy::{asset::LoadState, gltf::Gltf, prelude::*};
+use std::any::TypeId;
 
-use crate::{character::NameComponent, states::GameState};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    gltf::Gltf,
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+use thiserror::Error;
 
+use crate::{
+    ai::AiConfig,
+    behavior::BehaviorTree,
+    character::{HealthComponent, NameComponent},
+    combat::{Combo, ComboStats, MeleeAttack, MeleeStats},
+    health::DamageResistances,
+    loading::{LoadingTracker, TrackLoading},
+    movable::{AnimatedCharacterMovable, Movable},
+    perception::{Perception, PerceptionStats},
+    states::GameState,
+};
+
+// Folder scanned for `*.character.ron` manifests, one per character archetype
+const CHARACTERS_FOLDER: &str = "characters";
+
+// Character manifest as written on disk
+#[derive(Deserialize, Debug)]
+pub struct CharacterManifest {
+    pub name: String,
+    pub model: String,
+    pub scene: String,
+    // Name of the scene node holding the AnimationPlayer, needed when the scene has several
+    #[serde(default)]
+    pub animation_player: Option<String>,
+    pub animations: AnimationRoles,
+    pub movable: MovableStats,
+    #[serde(default = "default_health")]
+    pub health: f32,
+    #[serde(default)]
+    pub resistances: DamageResistances,
+    // Needs the `attack` animation
+    #[serde(default)]
+    pub melee: Option<MeleeStats>,
+    #[serde(default)]
+    pub combo: Option<ComboStats>,
+    // Path of a `*.behavior.ron` tree driving the character as an enemy
+    #[serde(default)]
+    pub behavior: Option<String>,
+    // Senses when the character is an enemy
+    #[serde(default)]
+    pub perception: PerceptionStats,
+    // State machine tuning when the character is an enemy without a behavior tree
+    #[serde(default)]
+    pub ai: AiConfig,
+    // Characters without one are loaded but never spawned
+    #[serde(default)]
+    pub spawn: Option<SpawnStats>,
+}
+
+fn default_health() -> f32 {
+    100.0
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnRole {
+    // Only one character can be the player
+    Player,
+    Enemy,
+}
+
+// Where and as what a character enters the level
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpawnStats {
+    pub role: SpawnRole,
+    // Feet of the first character
+    pub position: (f32, f32, f32),
+    // Enemies only, the player is always alone
+    #[serde(default = "default_spawn_count")]
+    pub count: usize,
+    // Offset from one character to the next
+    #[serde(default)]
+    pub spacing: (f32, f32, f32),
+    // Name shown in game, numbered when there are several, the manifest name by default
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+fn default_spawn_count() -> usize {
+    1
+}
+
+impl SpawnStats {
+    // Name and feet position of every character to spawn
+    pub fn spawn_points(&self, character_name: &str) -> Vec<(String, Vec3)> {
+        let name = self.name.as_deref().unwrap_or(character_name);
+        let position = Vec3::from(self.position);
+        let spacing = Vec3::from(self.spacing);
+        (0..self.count)
+            .map(|i| {
+                let name = if self.count > 1 {
+                    format!("{name} {}", i + 1)
+                } else {
+                    name.to_string()
+                };
+                (name, position + spacing * i as f32)
+            })
+            .collect()
+    }
+}
+
+// Animation role -> glTF animation name, either the full name or the part after the
+// last `|` (Blender exports "Armature|Armature|Run", the manifest can just say "Run")
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnimationRoles {
+    pub run: String,
+    pub walk: String,
+    pub idle: Vec<String>,
+    #[serde(default)]
+    pub attack: Option<String>,
+    pub death: String,
+    #[serde(default)]
+    pub take_damage: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct MovableStats {
+    pub max_speed: f32,
+    pub acceleration: f32,
+    pub deceleration: f32,
+    // Radians per second
+    pub turn_rate: f32,
+}
+
+// Animation clips resolved from the glTF for every role of the manifest
+#[derive(Debug, Clone)]
+pub struct CharacterClips {
+    pub run: Handle<AnimationClip>,
+    pub walk: Handle<AnimationClip>,
+    pub idle: Vec<Handle<AnimationClip>>,
+    pub attack: Option<Handle<AnimationClip>>,
+    pub death: Handle<AnimationClip>,
+    pub take_damage: Option<Handle<AnimationClip>>,
+    // One clip per combo attack, in the manifest order
+    pub combo: Vec<Handle<AnimationClip>>,
+}
+
+// Everything needed to spawn one character archetype
+#[derive(Asset, TypePath, Debug)]
+pub struct CharacterAsset {
+    pub name: String,
+    pub model: String,
+    #[dependency]
+    pub scene: Handle<Scene>,
+    #[dependency]
+    pub gltf: Handle<Gltf>,
+    pub animation_player: Option<String>,
+    pub animations: AnimationRoles,
+    // Filled by `resolve_animations` once the glTF is loaded, again after a hot reload
+    pub clips: Option<CharacterClips>,
+    pub movable: MovableStats,
+    pub health: f32,
+    pub resistances: DamageResistances,
+    pub melee: Option<MeleeStats>,
+    pub combo: Option<ComboStats>,
+    #[dependency]
+    pub behavior: Option<Handle<BehaviorTree>>,
+    pub perception: PerceptionStats,
+    pub ai: AiConfig,
+    pub spawn: Option<SpawnStats>,
+}
+
+#[derive(Debug, Error)]
+#[error(
+    "{character}: no animation named \"{requested}\" in {model}, available clips: {available:?}"
+)]
+pub struct MissingAnimationError {
+    pub character: String,
+    pub model: String,
+    pub requested: String,
+    pub available: Vec<String>,
+}
+
+impl CharacterAsset {
+    pub fn movable(&self) -> Movable {
+        Movable {
+            max_speed: self.movable.max_speed,
+            acceleration: self.movable.acceleration,
+            deceleration: self.movable.deceleration,
+            turn_rate: self.movable.turn_rate,
+            ..Default::default()
+        }
+    }
+
+    pub fn perception(&self) -> Perception {
+        Perception::new(self.perception)
+    }
+
+    pub fn health(&self) -> HealthComponent {
+        HealthComponent::new(self.health)
+    }
+
+    pub fn melee_attack(&self) -> Option<MeleeAttack> {
+        let attack = self.clips.as_ref()?.attack.clone()?;
+        Some(MeleeAttack::new(self.melee?, attack))
+    }
+
+    pub fn combo(&self) -> Option<Combo> {
+        let animations = self.clips.as_ref()?.combo.clone();
+        Some(Combo::new(self.combo.clone()?, animations))
+    }
+
+    pub fn animation_player_node(&self) -> Option<AnimationPlayerNode> {
+        self.animation_player.clone().map(AnimationPlayerNode)
+    }
+
+    pub fn animated_movable(&self) -> AnimatedCharacterMovable {
+        let clips = self
+            .clips
+            .as_ref()
+            .expect("character animations are resolved while loading");
+        AnimatedCharacterMovable {
+            run_animation: clips.run.clone(),
+            walk_animation: clips.walk.clone(),
+            idle_animations: clips.idle.clone(),
+            take_damage_animation: clips.take_damage.clone(),
+            death_animation: clips.death.clone(),
+        }
+    }
+
+    // Look up every animation role by name in the loaded glTF
+    pub fn resolve_animations(&mut self, gltf: &Gltf) -> Result<(), MissingAnimationError> {
+        let find = |requested: &str| {
+            find_named_animation(gltf, requested).ok_or_else(|| {
+                let mut available: Vec<String> = gltf.named_animations.keys().cloned().collect();
+                available.sort();
+                MissingAnimationError {
+                    character: self.name.clone(),
+                    model: self.model.clone(),
+                    requested: requested.to_string(),
+                    available,
+                }
+            })
+        };
+
+        let roles = &self.animations;
+        let clips = CharacterClips {
+            run: find(&roles.run)?,
+            walk: find(&roles.walk)?,
+            idle: roles
+                .idle
+                .iter()
+                .map(|name| find(name))
+                .collect::<Result<_, _>>()?,
+            attack: roles.attack.as_deref().map(find).transpose()?,
+            death: find(&roles.death)?,
+            take_damage: roles.take_damage.as_deref().map(find).transpose()?,
+            combo: self
+                .combo
+                .iter()
+                .flat_map(|combo| &combo.attacks)
+                .map(|attack| find(&attack.animation))
+                .collect::<Result<_, _>>()?,
+        };
+        self.clips = Some(clips);
+        Ok(())
+    }
+}
+
+pub fn find_named_animation(gltf: &Gltf, name: &str) -> Option<Handle<AnimationClip>> {
+    if let Some(clip) = gltf.named_animations.get(name) {
+        return Some(clip.clone());
+    }
+    gltf.named_animations
+        .iter()
+        .find(|(clip_name, _)| clip_name.rsplit('|').next() == Some(name))
+        .map(|(_, clip)| clip.clone())
+}
+
+#[derive(Default)]
+pub struct CharacterAssetLoader;
+
+#[derive(Debug, Error)]
+pub enum CharacterAssetLoaderError {
+    #[error("could not read character manifest: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse character manifest: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("{0}: at least one idle animation is needed")]
+    NoIdleAnimation(String),
+}
+
+impl AssetLoader for CharacterAssetLoader {
+    type Asset = CharacterAsset;
+    type Settings = ();
+    type Error = CharacterAssetLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<CharacterAsset, CharacterAssetLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let manifest: CharacterManifest = ron::de::from_bytes(&bytes)?;
+            if manifest.animations.idle.is_empty() {
+                return Err(CharacterAssetLoaderError::NoIdleAnimation(manifest.name));
+            }
+
+            Ok(CharacterAsset {
+                scene: load_context.load(format!("{}#{}", manifest.model, manifest.scene)),
+                gltf: load_context.load(&manifest.model),
+                name: manifest.name,
+                model: manifest.model,
+                animation_player: manifest.animation_player,
+                animations: manifest.animations,
+                clips: None,
+                movable: manifest.movable,
+                health: manifest.health,
+                resistances: manifest.resistances,
+                melee: manifest.melee,
+                combo: manifest.combo,
+                behavior: manifest.behavior.map(|path| load_context.load(path)),
+                perception: manifest.perception,
+                ai: manifest.ai,
+                spawn: manifest.spawn,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["character.ron"]
+    }
+}
+
+// Character archetypes indexed by the manifest name, filled once the folder is loaded
 #[derive(Resource, Debug, Default)]
-pub struct PlayerSceneAssets {
-    pub player: Handle<Scene>,
-    pub player_glb: Handle<Gltf>,
-    pub player_death_animation: Handle<AnimationClip>,
-    pub player_run_animation: Handle<AnimationClip>,
-    pub player_walk_animation: Handle<AnimationClip>,
-    pub player_take_damage_animation: Handle<AnimationClip>,
-    pub player_idle_animations: Vec<Handle<AnimationClip>>,
-    pub chess_texture: Handle<Image>,
+pub struct CharacterAssets {
+    pub folder: Handle<LoadedFolder>,
+    pub archetypes: HashMap<String, Handle<CharacterAsset>>,
+}
+
+impl CharacterAssets {
+    // Characters spawned with this role, sorted by name. Only those whose animations are
+    // resolved, a reloaded manifest can have broken ones
+    pub fn with_role<'a>(
+        &self,
+        role: SpawnRole,
+        characters: &'a Assets<CharacterAsset>,
+    ) -> Vec<&'a CharacterAsset> {
+        let mut found: Vec<&CharacterAsset> = self
+            .archetypes
+            .values()
+            .filter_map(|handle| characters.get(handle))
+            .filter(|character| {
+                character.clips.is_some()
+                    && character
+                        .spawn
+                        .as_ref()
+                        .is_some_and(|spawn| spawn.role == role)
+            })
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        found
+    }
+
+    // The character played, the first one if several manifests claim it
+    pub fn player<'a>(&self, characters: &'a Assets<CharacterAsset>) -> Option<&'a CharacterAsset> {
+        let players = self.with_role(SpawnRole::Player, characters);
+        if players.len() > 1 {
+            warn!("Several player characters, using {}", players[0].name);
+        }
+        players.first().copied()
+    }
 }
 
 #[derive(Resource, Debug, Default)]
-pub struct SkeletonSceneAssets {
-    pub skeleton: Handle<Scene>,
-    pub skeleton_attack_animation: Handle<AnimationClip>,
-    pub skeleton_death_animation: Handle<AnimationClip>,
-    pub skeleton_run_animation: Handle<AnimationClip>,
-    pub skeleton_walk_animation: Handle<AnimationClip>,
-    pub skeleton_take_damage_animation: Handle<AnimationClip>,
-    pub skeleton_idle_animations: Vec<Handle<AnimationClip>>,
+pub struct LevelAssets {
+    pub chess_texture: Handle<Image>,
 }
 
 // Animation Entity link to link entity root to animation player
 #[derive(Component)]
 pub struct AnimationEntityLink(pub Entity);
 
+// Only link the AnimationPlayer found on the scene node with this name
+#[derive(Component)]
+pub struct AnimationPlayerNode(pub String);
+
+// Running while a character waits for its AnimationPlayer, reported once it finishes
+#[derive(Component)]
+pub struct AnimationLinkTimeout(Timer);
+
+const ANIMATION_LINK_TIMEOUT: f32 = 5.0;
+
 pub struct AssetLoaderPlugin;
 
 impl Plugin for AssetLoaderPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<PlayerSceneAssets>()
-            .init_resource::<SkeletonSceneAssets>()
-            .add_systems(PreStartup, (load_player_assets, load_skeleton_assets))
-            .add_systems(Update, link_animators)
+        app.init_asset::<CharacterAsset>()
+            .init_asset_loader::<CharacterAssetLoader>()
+            .init_resource::<CharacterAssets>()
+            .init_resource::<LevelAssets>()
+            .add_systems(PreStartup, (load_character_assets, load_level_assets))
             .add_systems(
                 Update,
-                check_loading_status.run_if(in_state(GameState::Loading)),
+                (
+                    unlink_stale_animators,
+                    link_animators,
+                    report_unlinked_animators,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, resolve_reloaded_characters)
+            .add_systems(
+                Update,
+                (track_character_assets, index_character_assets)
+                    .chain()
+                    .in_set(TrackLoading)
+                    .run_if(in_state(GameState::Loading)),
             );
     }
 }
 
-fn check_loading_status(
-    mut game_state: ResMut<NextState<GameState>>,
-    player_assets: ResMut<PlayerSceneAssets>,
+// Register manifests and their models with the loading tracker as soon as they are known
+fn track_character_assets(
+    mut tracker: ResMut<LoadingTracker>,
+    character_assets: Res<CharacterAssets>,
+    folders: Res<Assets<LoadedFolder>>,
+    characters: Res<Assets<CharacterAsset>>,
+) {
+    let Some(folder) = folders.get(&character_assets.folder) else {
+        return;
+    };
+    for handle in folder.handles.iter() {
+        tracker.track(handle.clone());
+        if handle.type_id() != TypeId::of::<CharacterAsset>() {
+            continue;
+        }
+        if let Some(character) = characters.get(handle.id().typed::<CharacterAsset>()) {
+            tracker.track(character.scene.clone().untyped());
+            tracker.track(character.gltf.clone().untyped());
+        }
+    }
+}
+
+fn index_character_assets(
+    mut tracker: ResMut<LoadingTracker>,
+    mut character_assets: ResMut<CharacterAssets>,
     asset_server: Res<AssetServer>,
-    mut skeleton_assets: ResMut<SkeletonSceneAssets>,
+    folders: Res<Assets<LoadedFolder>>,
+    gltfs: Res<Assets<Gltf>>,
+    mut characters: ResMut<Assets<CharacterAsset>>,
 ) {
-    if asset_server.is_loaded_with_dependencies(&player_assets.player_glb) {
-        println!("Loaded, Start game");
-        game_state.set(GameState::Playing);
-    } else {
-        println!("Loading");
+    if !character_assets.archetypes.is_empty()
+        || !asset_server.is_loaded_with_dependencies(&character_assets.folder)
+    {
+        return;
+    }
+
+    let folder = folders.get(&character_assets.folder).unwrap();
+    for handle in folder.handles.iter() {
+        if handle.type_id() != TypeId::of::<CharacterAsset>() {
+            continue;
+        }
+        let handle = handle.clone().typed::<CharacterAsset>();
+        let character = characters.get_mut(&handle).unwrap();
+        let gltf = gltfs.get(&character.gltf).unwrap();
+        if let Err(error) = character.resolve_animations(gltf) {
+            tracker.fail(character.model.clone(), error.to_string());
+            continue;
+        }
+        info!("Loaded character {}", character.name);
+        character_assets
+            .archetypes
+            .insert(character.name.clone(), handle);
+    }
+}
+
+// A hot reloaded manifest replaces the asset without clips, resolve them again
+fn resolve_reloaded_characters(
+    mut character_events: EventReader<AssetEvent<CharacterAsset>>,
+    mut character_assets: ResMut<CharacterAssets>,
+    gltfs: Res<Assets<Gltf>>,
+    mut characters: ResMut<Assets<CharacterAsset>>,
+) {
+    for event in character_events.read() {
+        let (AssetEvent::Modified { id } | AssetEvent::LoadedWithDependencies { id }) = *event
+        else {
+            continue;
+        };
+        // Characters not indexed yet are resolved by `index_character_assets`
+        let Some(old_name) = character_assets
+            .archetypes
+            .iter()
+            .find(|(_, handle)| handle.id() == id)
+            .map(|(name, _)| name.clone())
+        else {
+            continue;
+        };
+        let Some(character) = characters.get_mut(id) else {
+            continue;
+        };
+        if character.clips.is_some() {
+            continue;
+        }
+        // Not loaded yet, LoadedWithDependencies follows once it is
+        let Some(gltf) = gltfs.get(&character.gltf) else {
+            continue;
+        };
+        if let Err(error) = character.resolve_animations(gltf) {
+            error!("Could not reload character {}: {error}", character.name);
+            continue;
+        }
+        info!("Reloaded character {}", character.name);
+        if character.name != old_name {
+            let handle = character_assets.archetypes.remove(&old_name).unwrap();
+            character_assets
+                .archetypes
+                .insert(character.name.clone(), handle);
+        }
+    }
+}
+
+// Drop links whose player is gone or whose scene was swapped, so they get linked again
+fn unlink_stale_animators(
+    links: Query<(Entity, &AnimationEntityLink, Ref<Handle<Scene>>)>,
+    animation_players: Query<(), With<AnimationPlayer>>,
+    parents: Query<&Parent>,
+    mut commands: Commands,
+) {
+    for (character, link, scene) in links.iter() {
+        let stale = scene.is_changed()
+            || animation_players.get(link.0).is_err()
+            || !parents
+                .iter_ancestors(link.0)
+                .any(|ancestor| ancestor == character);
+        if stale {
+            commands.entity(character).remove::<AnimationEntityLink>();
+        }
     }
 }
 
 fn link_animators(
-    players: Query<(Entity, &NameComponent), Without<AnimationEntityLink>>,
-    animation_players: Query<&mut AnimationPlayer, Added<AnimationPlayer>>,
+    players: Query<
+        (Entity, &NameComponent, Option<&AnimationPlayerNode>),
+        Without<AnimationEntityLink>,
+    >,
+    animation_players: Query<Option<&Name>, With<AnimationPlayer>>,
     children: Query<&Children>,
     mut commands: Commands,
 ) {
     // Get all players in the scene (not animation players, just the game Character)
-    for (player, name) in players.iter() {
-        // Loop through all the player children in search for animation player
-        for entity_child in children.iter_descendants(player) {
-            if let Ok(_) = animation_players.get(entity_child) {
-                // If animation player was found for the Child id, add it to the main node as an Entity ref
-                commands
-                    .entity(player)
-                    .insert(AnimationEntityLink(entity_child.clone()));
-                println!("Found animation player for {}!", name.0);
-                break;
+    for (player, name, node) in players.iter() {
+        // Collect every animation player below the character, nested scenes included
+        let candidates: Vec<(Entity, Option<&Name>)> = children
+            .iter_descendants(player)
+            .filter_map(|child| Some((child, animation_players.get(child).ok()?)))
+            .collect();
+
+        let found = match node {
+            Some(node) => candidates
+                .iter()
+                .find(|(_, node_name)| node_name.is_some_and(|n| n.as_str() == node.0)),
+            None => {
+                if candidates.len() > 1 {
+                    warn!(
+                        "{} has {} animation players, linking the first one",
+                        name.0,
+                        candidates.len()
+                    );
+                }
+                candidates.first()
             }
+        };
+
+        if let Some((animation_player, _)) = found {
+            // Add the animation player to the main node as an Entity ref
+            commands
+                .entity(player)
+                .insert(AnimationEntityLink(*animation_player))
+                .remove::<AnimationLinkTimeout>();
+            debug!("Found animation player for {}", name.0);
         }
     }
 }
 
-fn load_player_assets(mut scene_assets: ResMut<PlayerSceneAssets>, asset_server: Res<AssetServer>) {
-    *scene_assets = PlayerSceneAssets {
-        player: asset_server.load("Steve.glb#Scene0"),
-        player_glb: asset_server.load("Steve.glb"),
-        player_death_animation: asset_server.load("Steve.glb#Animation0"),
-        player_run_animation: asset_server.load("Steve.glb#Animation12"),
-        player_walk_animation: asset_server.load("Steve.glb#Animation14"),
-        player_take_damage_animation: asset_server.load("Steve.glb#Animation2"),
-        player_idle_animations: vec![
-            asset_server.load("Steve.glb#Animation4"),
-            asset_server.load("Steve.glb#Animation3"),
-            asset_server.load("Steve.glb#Animation5"),
-        ],
-        chess_texture: asset_server.load("chess.jpg"),
-    };
+fn report_unlinked_animators(
+    mut commands: Commands,
+    mut unlinked: Query<
+        (Entity, &NameComponent, Option<&mut AnimationLinkTimeout>),
+        Without<AnimationEntityLink>,
+    >,
+    time: Res<Time>,
+) {
+    for (character, name, timeout) in unlinked.iter_mut() {
+        let Some(mut timeout) = timeout else {
+            commands
+                .entity(character)
+                .insert(AnimationLinkTimeout(Timer::from_seconds(
+                    ANIMATION_LINK_TIMEOUT,
+                    TimerMode::Once,
+                )));
+            continue;
+        };
+        if timeout.0.tick(time.delta()).just_finished() {
+            warn!(
+                "{} still has no AnimationPlayer after {}s",
+                name.0, ANIMATION_LINK_TIMEOUT
+            );
+        }
+    }
 }
 
-fn load_skeleton_assets(
-    mut scene_assets: ResMut<SkeletonSceneAssets>,
+fn load_character_assets(
+    mut character_assets: ResMut<CharacterAssets>,
+    mut tracker: ResMut<LoadingTracker>,
     asset_server: Res<AssetServer>,
 ) {
-    println!("Loading skeleton assets");
-    *scene_assets = SkeletonSceneAssets {
-        skeleton: asset_server.load("Skeleton.glb#Scene0"),
-        skeleton_attack_animation: asset_server.load("Skeleton.glb#Animation0"),
-        skeleton_death_animation: asset_server.load("Skeleton.glb#Animation1"),
-        skeleton_run_animation: asset_server.load("Skeleton.glb#Animation5"),
-        skeleton_walk_animation: asset_server.load("Skeleton.glb#Animation6"),
-        skeleton_take_damage_animation: asset_server.load("Skeleton.glb#Animation2"),
-        skeleton_idle_animations: vec![asset_server.load("Skeleton.glb#Animation3")],
-    }
+    character_assets.folder = asset_server.load_folder(CHARACTERS_FOLDER);
+    tracker.track(character_assets.folder.clone().untyped());
+}
+
+fn load_level_assets(
+    mut level_assets: ResMut<LevelAssets>,
+    mut tracker: ResMut<LoadingTracker>,
+    asset_server: Res<AssetServer>,
+) {
+    *level_assets = LevelAssets {
+        chess_texture: asset_server.load("chess.jpg"),
+    };
+    tracker.track(level_assets.chess_texture.clone().untyped());
 }
@@ -0,0 +1,670 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    gltf::Gltf,
+    prelude::*,
+    utils::{BoxedFuture, HashMap, HashSet},
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    ai::{chase, ATTACK_ANGLE},
+    asset_loader::{find_named_animation, AnimationEntityLink},
+    character::HealthComponent,
+    combat::{Attacking, MeleeAttack},
+    enemy::EnemyTag,
+    health::Dead,
+    movable::{HitReaction, Movable, ScriptedAnimation},
+    navigation::NavPath,
+    perception::{Perception, PerceptionSet},
+    states::gameplay_running,
+    steering,
+};
+
+// Enemy AI authored in `*.behavior.ron` files, referenced by the character manifest.
+// The whole tree is ticked from the root every frame, so a higher priority branch
+// takes over as soon as its conditions hold
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct BehaviorTree {
+    pub root: BehaviorNode,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum BehaviorNode {
+    // Ticks the children in order while they succeed
+    Sequence(Vec<BehaviorNode>),
+    // Ticks the children in order until one doesn't fail
+    Selector(Vec<BehaviorNode>),
+    // Swaps success and failure
+    Invert(Box<BehaviorNode>),
+    // Succeeds even when the child fails
+    Succeed(Box<BehaviorNode>),
+    // Fails for that many seconds once the child succeeded
+    Cooldown(f32, Box<BehaviorNode>),
+    // Only a player in view counts
+    PlayerWithin(f32),
+    // Fraction of the max health
+    HealthBelow(f32),
+    MoveTo {
+        target: MoveTarget,
+        stop_distance: f32,
+        // Runs instead of walking once the target is this close
+        #[serde(default)]
+        run_within: f32,
+    },
+    // Melee attack on the player, running while on cooldown
+    Attack,
+    Wait(f32),
+    // Clip name in the character's glTF, running until it is over
+    PlayAnimation(String),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum MoveTarget {
+    // Fails when out of view
+    Player,
+    // Where the player was last seen or heard, fails once forgotten
+    LastKnown,
+    // Where the enemy spawned
+    Home,
+}
+
+impl BehaviorNode {
+    // Nodes in this subtree, ids are given in depth first order
+    fn size(&self) -> usize {
+        1 + self.children().map(BehaviorNode::size).sum::<usize>()
+    }
+
+    // Calls `visitor` with every node of this subtree and its id
+    fn visit(&self, id: usize, visitor: &mut impl FnMut(usize, &BehaviorNode)) {
+        visitor(id, self);
+        let mut child_id = id + 1;
+        for child in self.children() {
+            child.visit(child_id, visitor);
+            child_id += child.size();
+        }
+    }
+
+    fn children(&self) -> impl Iterator<Item = &BehaviorNode> {
+        let children: &[BehaviorNode] = match self {
+            BehaviorNode::Sequence(children) | BehaviorNode::Selector(children) => children,
+            BehaviorNode::Invert(child)
+            | BehaviorNode::Succeed(child)
+            | BehaviorNode::Cooldown(_, child) => std::slice::from_ref(child.as_ref()),
+            _ => &[],
+        };
+        children.iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BehaviorNode::Sequence(_) => "Sequence",
+            BehaviorNode::Selector(_) => "Selector",
+            BehaviorNode::Invert(_) => "Invert",
+            BehaviorNode::Succeed(_) => "Succeed",
+            BehaviorNode::Cooldown(..) => "Cooldown",
+            BehaviorNode::PlayerWithin(_) => "PlayerWithin",
+            BehaviorNode::HealthBelow(_) => "HealthBelow",
+            BehaviorNode::MoveTo { .. } => "MoveTo",
+            BehaviorNode::Attack => "Attack",
+            BehaviorNode::Wait(_) => "Wait",
+            BehaviorNode::PlayAnimation(_) => "PlayAnimation",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+// Runs a behavior tree for one enemy
+#[derive(Component, Debug)]
+pub struct BehaviorRunner {
+    pub tree: Handle<BehaviorTree>,
+    // Model the PlayAnimation clips are looked up in
+    gltf: Handle<Gltf>,
+    home: Vec3,
+    // Seconds spent in Wait and PlayAnimation nodes by node id, forgotten once they aren't ticked
+    elapsed: HashMap<usize, f32>,
+    // Seconds left on Cooldown nodes by node id
+    cooldowns: HashMap<usize, f32>,
+    // Clips of the PlayAnimation nodes by node id, looked up once the tree and model are
+    // loaded. Nodes whose clip is missing are left out and fail
+    animations: Option<HashMap<usize, Handle<AnimationClip>>>,
+    // Last leaf that ran, for the debug readout
+    pub active: &'static str,
+}
+
+impl BehaviorRunner {
+    pub fn new(tree: Handle<BehaviorTree>, gltf: Handle<Gltf>, home: Vec3) -> Self {
+        Self {
+            tree,
+            gltf,
+            home,
+            elapsed: HashMap::default(),
+            cooldowns: HashMap::default(),
+            animations: None,
+            active: "",
+        }
+    }
+
+    fn reset(&mut self) {
+        self.elapsed.clear();
+        self.cooldowns.clear();
+        self.animations = None;
+        self.active = "";
+    }
+
+    // Missing clips are reported here, once, instead of on every tick
+    fn resolve_animations(&mut self, tree: &BehaviorTree, gltf: &Gltf) {
+        let mut animations = HashMap::default();
+        tree.root.visit(0, &mut |id, node| {
+            let BehaviorNode::PlayAnimation(name) = node else {
+                return;
+            };
+            match find_named_animation(gltf, name) {
+                Some(clip) => {
+                    animations.insert(id, clip);
+                }
+                None => warn!(
+                    "No animation named \"{name}\" for a behavior tree, its PlayAnimation fails"
+                ),
+            }
+        });
+        self.animations = Some(animations);
+    }
+}
+
+// Everything a tick reads and writes for one enemy
+struct BehaviorContext<'a> {
+    runner: &'a mut BehaviorRunner,
+    movable: &'a mut Movable,
+    transform: &'a Transform,
+    nav_path: &'a mut NavPath,
+    melee_attack: Option<&'a MeleeAttack>,
+    health_fraction: f32,
+    // The player, when in view
+    player: Option<Vec3>,
+    last_known_position: Option<Vec3>,
+    clips: &'a Assets<AnimationClip>,
+    delta_seconds: f32,
+    ticked: HashSet<usize>,
+    // Requests applied once the tick is over
+    start_attack: bool,
+    play_animation: Option<(Handle<AnimationClip>, f32)>,
+}
+
+impl BehaviorContext<'_> {
+    // One frame of the tree, from the root
+    fn run(&mut self, root: &BehaviorNode) -> Status {
+        let delta_seconds = self.delta_seconds;
+        self.runner.cooldowns.retain(|_, seconds| {
+            *seconds -= delta_seconds;
+            *seconds > 0.0
+        });
+        let status = self.tick(root, 0);
+        // Nodes not ticked this frame start over next time
+        let ticked = &self.ticked;
+        self.runner.elapsed.retain(|id, _| ticked.contains(id));
+        status
+    }
+
+    fn tick(&mut self, node: &BehaviorNode, id: usize) -> Status {
+        self.ticked.insert(id);
+        match node {
+            BehaviorNode::Sequence(children) => {
+                let mut child_id = id + 1;
+                for child in children {
+                    let status = self.tick(child, child_id);
+                    if status != Status::Success {
+                        return status;
+                    }
+                    child_id += child.size();
+                }
+                Status::Success
+            }
+            BehaviorNode::Selector(children) => {
+                let mut child_id = id + 1;
+                for child in children {
+                    let status = self.tick(child, child_id);
+                    if status != Status::Failure {
+                        return status;
+                    }
+                    child_id += child.size();
+                }
+                Status::Failure
+            }
+            BehaviorNode::Invert(child) => match self.tick(child, id + 1) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            },
+            BehaviorNode::Succeed(child) => match self.tick(child, id + 1) {
+                Status::Running => Status::Running,
+                _ => Status::Success,
+            },
+            BehaviorNode::Cooldown(seconds, child) => {
+                if self.runner.cooldowns.contains_key(&id) {
+                    return Status::Failure;
+                }
+                let status = self.tick(child, id + 1);
+                if status == Status::Success {
+                    self.runner.cooldowns.insert(id, *seconds);
+                }
+                status
+            }
+            BehaviorNode::PlayerWithin(distance) => self
+                .player
+                .is_some_and(|player| self.transform.translation.distance(player) <= *distance)
+                .into(),
+            BehaviorNode::HealthBelow(fraction) => (self.health_fraction < *fraction).into(),
+            leaf => {
+                self.runner.active = leaf.name();
+                self.tick_action(leaf, id)
+            }
+        }
+    }
+
+    fn tick_action(&mut self, node: &BehaviorNode, id: usize) -> Status {
+        match node {
+            BehaviorNode::MoveTo {
+                target,
+                stop_distance,
+                run_within,
+            } => {
+                let target = match target {
+                    MoveTarget::Player => match self.player {
+                        Some(player) => player,
+                        None => return Status::Failure,
+                    },
+                    MoveTarget::LastKnown => match self.last_known_position {
+                        Some(position) => position,
+                        None => return Status::Failure,
+                    },
+                    MoveTarget::Home => self.runner.home,
+                };
+                if self.transform.translation.distance(target) <= *stop_distance {
+                    self.nav_path.clear();
+                    return Status::Success;
+                }
+                chase(
+                    self.movable,
+                    self.transform,
+                    self.nav_path,
+                    target,
+                    *stop_distance,
+                    *run_within,
+                );
+                Status::Running
+            }
+            BehaviorNode::Attack => {
+                let (Some(melee_attack), Some(player)) = (self.melee_attack, self.player) else {
+                    return Status::Failure;
+                };
+                steering::apply(
+                    self.movable,
+                    self.transform,
+                    steering::face(self.transform.translation, player),
+                );
+                if melee_attack.is_ready()
+                    && steering::is_facing(self.transform, player, ATTACK_ANGLE)
+                {
+                    self.start_attack = true;
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            BehaviorNode::Wait(seconds) => {
+                let elapsed = self.elapsed(id);
+                if elapsed >= *seconds {
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            BehaviorNode::PlayAnimation(_) => {
+                let Some(clip) = self
+                    .runner
+                    .animations
+                    .as_ref()
+                    .and_then(|animations| animations.get(&id))
+                    .cloned()
+                else {
+                    return Status::Failure;
+                };
+                let duration = self.clips.get(&clip).map_or(0.0, |clip| clip.duration());
+                if !self.runner.elapsed.contains_key(&id) {
+                    self.play_animation = Some((clip, duration));
+                }
+                if self.elapsed(id) >= duration {
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            _ => Status::Failure,
+        }
+    }
+
+    // Seconds since the node started running, counting this frame
+    fn elapsed(&mut self, id: usize) -> f32 {
+        let elapsed = self.runner.elapsed.entry(id).or_insert(-self.delta_seconds);
+        *elapsed += self.delta_seconds;
+        *elapsed
+    }
+}
+
+impl From<bool> for Status {
+    fn from(value: bool) -> Self {
+        if value {
+            Status::Success
+        } else {
+            Status::Failure
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BehaviorTreeLoader;
+
+#[derive(Debug, Error)]
+pub enum BehaviorTreeLoaderError {
+    #[error("could not read behavior tree: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse behavior tree: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for BehaviorTreeLoader {
+    type Asset = BehaviorTree;
+    type Settings = ();
+    type Error = BehaviorTreeLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<BehaviorTree, BehaviorTreeLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["behavior.ron"]
+    }
+}
+
+pub struct BehaviorPlugin;
+
+impl Plugin for BehaviorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<BehaviorTree>()
+            .init_asset_loader::<BehaviorTreeLoader>()
+            .add_systems(
+                Update,
+                (reload_behavior_trees, run_behavior_trees)
+                    .chain()
+                    .after(PerceptionSet)
+                    .run_if(gameplay_running),
+            );
+    }
+}
+
+// Trees are hot reloaded by the asset server, the runners start over on the new one
+fn reload_behavior_trees(
+    mut tree_events: EventReader<AssetEvent<BehaviorTree>>,
+    mut runners: Query<&mut BehaviorRunner>,
+) {
+    for event in tree_events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        info!("Behavior tree {id:?} reloaded");
+        for mut runner in runners.iter_mut() {
+            if runner.tree.id() == *id {
+                runner.reset();
+            }
+        }
+    }
+}
+
+fn run_behavior_trees(
+    mut commands: Commands,
+    mut enemies: Query<
+        (
+            Entity,
+            &mut BehaviorRunner,
+            &mut Movable,
+            &Transform,
+            &mut NavPath,
+            &HealthComponent,
+            &Perception,
+            Option<&MeleeAttack>,
+            Option<&AnimationEntityLink>,
+        ),
+        (
+            With<EnemyTag>,
+            Without<Dead>,
+            Without<HitReaction>,
+            Without<Attacking>,
+        ),
+    >,
+    mut animation_players: Query<&mut AnimationPlayer>,
+    trees: Res<Assets<BehaviorTree>>,
+    gltfs: Res<Assets<Gltf>>,
+    clips: Res<Assets<AnimationClip>>,
+    time: Res<Time>,
+) {
+    for (
+        entity,
+        mut runner,
+        mut movable,
+        transform,
+        mut nav_path,
+        health,
+        perception,
+        melee_attack,
+        animation_link,
+    ) in enemies.iter_mut()
+    {
+        let Some(tree) = trees.get(&runner.tree) else {
+            continue;
+        };
+        if runner.animations.is_none() {
+            if let Some(gltf) = gltfs.get(&runner.gltf) {
+                runner.resolve_animations(tree, gltf);
+            }
+        }
+        // Only the nodes ticked this frame move the enemy
+        movable.throttle = 0.0;
+        movable.heading = None;
+        nav_path.destination = None;
+
+        let mut context = BehaviorContext {
+            runner: &mut runner,
+            movable: &mut movable,
+            transform,
+            nav_path: &mut nav_path,
+            melee_attack,
+            health_fraction: health.current / health.max,
+            player: perception.visible_player(),
+            last_known_position: perception.last_known_position,
+            clips: &clips,
+            delta_seconds: time.delta_seconds(),
+            ticked: HashSet::default(),
+            start_attack: false,
+            play_animation: None,
+        };
+        context.run(&tree.root);
+
+        let BehaviorContext {
+            start_attack,
+            play_animation,
+            ..
+        } = context;
+
+        if start_attack {
+            if let Some(melee_attack) = melee_attack {
+                commands.entity(entity).insert(melee_attack.attacking());
+            }
+        }
+        if let Some((clip, duration)) = play_animation {
+            let Some(mut animator) =
+                animation_link.and_then(|link| animation_players.get_mut(link.0).ok())
+            else {
+                continue;
+            };
+            animator
+                .start_with_transition(clip, Duration::from_millis(200))
+                .set_speed(1.0);
+            commands
+                .entity(entity)
+                .insert(ScriptedAnimation(Timer::from_seconds(
+                    duration,
+                    TimerMode::Once,
+                )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BehaviorNode::*;
+
+    const DELTA_SECONDS: f32 = 0.25;
+
+    // One enemy the trees are ticked for, at full health with no player around
+    struct Enemy {
+        runner: BehaviorRunner,
+        movable: Movable,
+        nav_path: NavPath,
+        clips: Assets<AnimationClip>,
+        health_fraction: f32,
+    }
+
+    impl Enemy {
+        fn new() -> Self {
+            Self {
+                runner: BehaviorRunner::new(Handle::default(), Handle::default(), Vec3::ZERO),
+                movable: Movable::default(),
+                nav_path: NavPath::default(),
+                clips: Assets::default(),
+                health_fraction: 1.0,
+            }
+        }
+
+        fn run(&mut self, root: &BehaviorNode) -> Status {
+            BehaviorContext {
+                runner: &mut self.runner,
+                movable: &mut self.movable,
+                transform: &Transform::default(),
+                nav_path: &mut self.nav_path,
+                melee_attack: None,
+                health_fraction: self.health_fraction,
+                player: None,
+                last_known_position: None,
+                clips: &self.clips,
+                delta_seconds: DELTA_SECONDS,
+                ticked: HashSet::default(),
+                start_attack: false,
+                play_animation: None,
+            }
+            .run(root)
+        }
+    }
+
+    #[test]
+    fn selector_runs_the_first_child_that_does_not_fail() {
+        let mut enemy = Enemy::new();
+        let tree = Selector(vec![HealthBelow(0.5), Wait(1.0), Wait(2.0)]);
+        assert_eq!(enemy.run(&tree), Status::Running);
+        assert_eq!(enemy.runner.active, "Wait");
+        // Only the first Wait started
+        assert_eq!(enemy.runner.elapsed.keys().collect::<Vec<_>>(), [&2]);
+
+        assert_eq!(
+            enemy.run(&Selector(vec![HealthBelow(0.5)])),
+            Status::Failure
+        );
+    }
+
+    #[test]
+    fn sequence_stops_at_the_first_child_that_does_not_succeed() {
+        let mut enemy = Enemy::new();
+        let tree = Sequence(vec![HealthBelow(2.0), PlayerWithin(5.0), Wait(1.0)]);
+        assert_eq!(enemy.run(&tree), Status::Failure);
+        assert!(enemy.runner.elapsed.is_empty());
+
+        let tree = Sequence(vec![HealthBelow(2.0), Invert(Box::new(PlayerWithin(5.0)))]);
+        assert_eq!(enemy.run(&tree), Status::Success);
+    }
+
+    #[test]
+    fn running_nodes_resume_where_they_were() {
+        let mut enemy = Enemy::new();
+        // A node's first tick is at 0 seconds, each Wait takes three ticks
+        let tree = Sequence(vec![Wait(0.5), Wait(0.5)]);
+        let statuses: Vec<Status> = (0..5).map(|_| enemy.run(&tree)).collect();
+        assert_eq!(
+            statuses,
+            [
+                Status::Running,
+                Status::Running,
+                Status::Running,
+                Status::Running,
+                Status::Success
+            ]
+        );
+    }
+
+    #[test]
+    fn running_nodes_start_over_once_interrupted() {
+        let mut enemy = Enemy::new();
+        // Waiting is interrupted for a frame while the health is low
+        let tree = Selector(vec![HealthBelow(0.5), Wait(0.5)]);
+        assert_eq!(enemy.run(&tree), Status::Running);
+        enemy.health_fraction = 0.1;
+        assert_eq!(enemy.run(&tree), Status::Success);
+        enemy.health_fraction = 1.0;
+        let statuses: Vec<Status> = (0..3).map(|_| enemy.run(&tree)).collect();
+        assert_eq!(
+            statuses,
+            [Status::Running, Status::Running, Status::Success]
+        );
+    }
+
+    #[test]
+    fn cooldown_fails_until_it_is_over() {
+        let mut enemy = Enemy::new();
+        let tree = Cooldown(0.5, Box::new(Succeed(Box::new(PlayerWithin(1.0)))));
+        let statuses: Vec<Status> = (0..4).map(|_| enemy.run(&tree)).collect();
+        assert_eq!(
+            statuses,
+            [
+                Status::Success,
+                Status::Failure,
+                Status::Success,
+                Status::Failure
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_animations_fail() {
+        let mut enemy = Enemy::new();
+        enemy.runner.animations = Some(HashMap::default());
+        assert_eq!(enemy.run(&PlayAnimation("Dance".into())), Status::Failure);
+        assert!(enemy.runner.elapsed.is_empty());
+    }
+}
@@ -1,62 +1,410 @@
-use std::time::Duration;
-
 use bevy::{
-    core_pipeline::{
-        bloom::BloomSettings, experimental::taa::TemporalAntiAliasBundle, tonemapping::Tonemapping,
-    },
-    pbr::{ScreenSpaceAmbientOcclusionBundle, ScreenSpaceAmbientOcclusionSettings},
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
     prelude::*,
 };
+use bevy_rapier3d::{
+    geometry::{Collider, CollisionGroups},
+    pipeline::QueryFilter,
+    plugin::RapierContext,
+};
+
+use crate::{
+    movable::{model_forward, model_yaw},
+    player::PlayerTag,
+    states::{gameplay_running, GameState, StateScoped},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraFollowMode {
+    // Free look around the player with mouse / right stick
+    #[default]
+    Orbit,
+    // Fixed isometric angle, only the zoom can change
+    Isometric,
+    // Stays behind the player's back
+    Chase,
+    TopDown,
+}
+
+impl CameraFollowMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraFollowMode::Orbit => CameraFollowMode::Isometric,
+            CameraFollowMode::Isometric => CameraFollowMode::Chase,
+            CameraFollowMode::Chase => CameraFollowMode::TopDown,
+            CameraFollowMode::TopDown => CameraFollowMode::Orbit,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CameraSettings {
+    pub follow_mode: CameraFollowMode,
+    pub cycle_mode_key: KeyCode,
+    // Seconds for the camera to cover half of the remaining distance / angle to its target
+    pub position_half_life: f32,
+    pub rotation_half_life: f32,
+    // Pitch used by the chase mode, radians
+    pub chase_pitch: f32,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    // Radians
+    pub yaw: f32,
+    pub pitch: f32,
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+    // Radians per pixel of mouse motion
+    pub mouse_sensitivity: f32,
+    // Radians per second at full stick deflection
+    pub gamepad_sensitivity: f32,
+    pub gamepad_deadzone: f32,
+    // Distance per scroll line
+    pub zoom_speed: f32,
+    // Mouse motion only orbits while this button is held
+    pub orbit_button: MouseButton,
+    pub recenter_key: KeyCode,
+    pub recenter_button: GamepadButtonType,
+    // Height above the player's feet the camera orbits and looks at
+    pub pivot_height: f32,
+    // Radius of the sphere cast from the pivot to keep the camera out of walls
+    pub collision_radius: f32,
+    // Colliders the camera collides with, the player's own collider is always ignored
+    pub collision_groups: CollisionGroups,
+    // Half-life of the camera moving back out once nothing is in the way
+    pub collision_recover_half_life: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            follow_mode: CameraFollowMode::Orbit,
+            cycle_mode_key: KeyCode::C,
+            position_half_life: 0.3,
+            rotation_half_life: 0.08,
+            chase_pitch: 20f32.to_radians(),
+            distance: 28.0,
+            min_distance: 4.0,
+            max_distance: 40.0,
+            yaw: 45f32.to_radians(),
+            pitch: 10f32.to_radians(),
+            min_pitch: -5f32.to_radians(),
+            max_pitch: 80f32.to_radians(),
+            mouse_sensitivity: 0.005,
+            gamepad_sensitivity: 2.5,
+            gamepad_deadzone: 0.15,
+            zoom_speed: 2.0,
+            orbit_button: MouseButton::Right,
+            recenter_key: KeyCode::R,
+            recenter_button: GamepadButtonType::RightThumb,
+            pivot_height: 1.5,
+            collision_radius: 0.4,
+            collision_groups: CollisionGroups::default(),
+            collision_recover_half_life: 0.25,
+        }
+    }
+}
+
+// Orbit state of the camera around the player
+#[derive(Component, Debug)]
+pub struct OrbitCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+}
 
-use crate::{player::PlayerTag, states::GameState};
+impl OrbitCamera {
+    pub fn direction(&self) -> Vec3 {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, -self.pitch, 0.0) * Vec3::Z
+    }
 
-const CAMERA_DISTANCE: f32 = 20.0;
-const CAMERA_HEIGHT: f32 = 5.0;
-const CAMERA_OFFSET: Vec3 = Vec3::new(CAMERA_DISTANCE, CAMERA_HEIGHT, CAMERA_DISTANCE);
+    pub fn offset(&self) -> Vec3 {
+        self.direction() * self.distance
+    }
+}
+
+// Fraction of the remaining gap to close this frame, the same trajectory at any frame rate
+pub fn smoothing_factor(half_life: f32, delta_seconds: f32) -> f32 {
+    if half_life <= 0.0 {
+        return 1.0;
+    }
+    1.0 - 0.5f32.powf(delta_seconds / half_life)
+}
+
+// Direction from the player to the camera for the current follow mode
+fn follow_direction(
+    mode: CameraFollowMode,
+    orbit: &OrbitCamera,
+    player_transform: &Transform,
+    settings: &CameraSettings,
+) -> Vec3 {
+    let from_angles =
+        |yaw: f32, pitch: f32| Quat::from_euler(EulerRot::YXZ, yaw, -pitch, 0.0) * Vec3::Z;
+    match mode {
+        CameraFollowMode::Orbit => orbit.direction(),
+        CameraFollowMode::Isometric => from_angles(45f32.to_radians(), 35.264f32.to_radians()),
+        CameraFollowMode::Chase => {
+            let behind = -model_forward(player_transform);
+            from_angles(model_yaw(behind), settings.chase_pitch)
+        }
+        // Not straight down so look_at keeps a stable up vector
+        CameraFollowMode::TopDown => from_angles(0.0, 85f32.to_radians()),
+    }
+}
+
+// Distance the camera is currently allowed to be from the player, shortened by obstacles
+#[derive(Component, Debug)]
+pub struct CameraCollision {
+    pub distance: f32,
+}
+
+// Systems moving the camera after the player, effects layered on top run after this set
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CameraFollow;
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), spawn_camera)
-            .add_systems(Update, player_camera.run_if(in_state(GameState::Playing)));
+        app.init_resource::<CameraSettings>()
+            .add_systems(OnEnter(GameState::Playing), spawn_camera)
+            .add_systems(
+                Update,
+                (orbit_camera_input, player_camera)
+                    .chain()
+                    .in_set(CameraFollow)
+                    .run_if(gameplay_running),
+            );
     }
 }
 
-fn spawn_camera(mut commands: Commands) {
+fn spawn_camera(mut commands: Commands, settings: Res<CameraSettings>) {
+    let orbit = OrbitCamera {
+        yaw: settings.yaw,
+        pitch: settings.pitch,
+        distance: settings.distance,
+    };
+
+    // Post-processing (bloom, SSAO, anti-aliasing, HDR, tonemapping) comes from GraphicsSettings
     commands
         .spawn(Camera3dBundle {
-            // camera: Camera {
-            //     hdr: true,
-            //     ..Default::default()
-            // },
-            transform: Transform::from_xyz(CAMERA_DISTANCE, CAMERA_DISTANCE, CAMERA_DISTANCE),
-            tonemapping: Tonemapping::TonyMcMapface,
+            transform: Transform::from_translation(orbit.offset()),
             ..Default::default()
         })
-        .insert(BloomSettings::NATURAL)
-        .insert(ScreenSpaceAmbientOcclusionBundle::default())
-        .insert(TemporalAntiAliasBundle::default())
-        .insert(ScreenSpaceAmbientOcclusionSettings {
-            quality_level: bevy::pbr::ScreenSpaceAmbientOcclusionQualityLevel::Ultra,
-        });
+        .insert(CameraCollision {
+            distance: orbit.distance,
+        })
+        .insert(orbit)
+        .insert(StateScoped(GameState::Playing));
+}
+
+fn orbit_camera_input(
+    mut cameras: Query<&mut OrbitCamera>,
+    player: Query<&Transform, With<PlayerTag>>,
+    mut settings: ResMut<CameraSettings>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    time: Res<Time>,
+) {
+    if keyboard_input.just_pressed(settings.cycle_mode_key) {
+        settings.follow_mode = settings.follow_mode.next();
+        info!("Camera follow mode: {:?}", settings.follow_mode);
+    }
+
+    let mut rotation = Vec2::ZERO;
+    if mouse_buttons.pressed(settings.orbit_button) {
+        for motion in mouse_motion.read() {
+            rotation += motion.delta * settings.mouse_sensitivity;
+        }
+    } else {
+        mouse_motion.clear();
+    }
+
+    let mut zoom = 0.0;
+    for wheel in mouse_wheel.read() {
+        zoom += match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            // Roughly one line per 20 pixels
+            MouseScrollUnit::Pixel => wheel.y / 20.0,
+        };
+    }
+
+    let mut recenter = keyboard_input.just_pressed(settings.recenter_key);
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            let value = gamepad_axes
+                .get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.0);
+            if value.abs() < settings.gamepad_deadzone {
+                0.0
+            } else {
+                value
+            }
+        };
+        let stick = Vec2::new(
+            axis(GamepadAxisType::RightStickX),
+            // Stick up is positive, mouse up is negative
+            -axis(GamepadAxisType::RightStickY),
+        );
+        rotation += stick * settings.gamepad_sensitivity * time.delta_seconds();
+        recenter |=
+            gamepad_buttons.just_pressed(GamepadButton::new(gamepad, settings.recenter_button));
+    }
+
+    if settings.follow_mode != CameraFollowMode::Orbit {
+        rotation = Vec2::ZERO;
+        recenter = false;
+    }
+
+    for mut orbit in cameras.iter_mut() {
+        orbit.yaw -= rotation.x;
+        orbit.pitch = (orbit.pitch + rotation.y).clamp(settings.min_pitch, settings.max_pitch);
+        orbit.distance = (orbit.distance - zoom * settings.zoom_speed)
+            .clamp(settings.min_distance, settings.max_distance);
+
+        if recenter {
+            if let Ok(player_transform) = player.get_single() {
+                let behind = -model_forward(player_transform);
+                orbit.yaw = model_yaw(behind);
+            }
+        }
+    }
 }
 
 fn player_camera(
-    player: Query<&Transform, (With<PlayerTag>, Without<Camera3d>)>,
-    mut camera: Query<&mut Transform, (With<Camera3d>, Without<PlayerTag>)>,
+    player: Query<(Entity, &Transform), (With<PlayerTag>, Without<Camera3d>)>,
+    mut camera: Query<
+        (&mut Transform, &OrbitCamera, &mut CameraCollision),
+        (With<Camera3d>, Without<PlayerTag>),
+    >,
+    rapier_context: Res<RapierContext>,
+    settings: Res<CameraSettings>,
     time: Res<Time>,
 ) {
-    for player_transform in player.iter() {
-        for mut camera_transform in camera.iter_mut() {
-            //Look at the player
-            camera_transform.look_at(player_transform.translation, Vec3::Y);
+    let delta_seconds = time.delta_seconds();
+    for (player_entity, player_transform) in player.iter() {
+        for (mut camera_transform, orbit, mut collision) in camera.iter_mut() {
+            let direction =
+                follow_direction(settings.follow_mode, orbit, player_transform, &settings);
+            // Chest height, a sphere cast from the feet would start inside the floor
+            let pivot = player_transform.translation + Vec3::Y * settings.pivot_height;
+
+            // Sweep a sphere from the pivot towards the wanted camera position
+            let filter = QueryFilter::new()
+                .exclude_sensors()
+                .exclude_collider(player_entity)
+                .exclude_rigid_body(player_entity)
+                .groups(settings.collision_groups);
+            let hit = rapier_context.cast_shape(
+                pivot,
+                Quat::IDENTITY,
+                direction,
+                &Collider::ball(settings.collision_radius),
+                orbit.distance,
+                filter,
+            );
+
+            let allowed = hit.map_or(orbit.distance, |(_, toi)| toi.toi);
+            let occluded = hit.is_some() && allowed < collision.distance;
+            if occluded {
+                collision.distance = allowed;
+            } else {
+                collision.distance += (allowed - collision.distance)
+                    * smoothing_factor(settings.collision_recover_half_life, delta_seconds);
+            }
 
             //Move the camera to the right distance
-            camera_transform.translation = camera_transform.translation.lerp(
-                player_transform.translation + CAMERA_OFFSET,
-                time.delta_seconds(),
+            let target = pivot + direction * collision.distance;
+            camera_transform.translation = if occluded {
+                // Pull in right away so the camera never ends up inside geometry
+                target
+            } else {
+                camera_transform.translation.lerp(
+                    target,
+                    smoothing_factor(settings.position_half_life, delta_seconds),
+                )
+            };
+
+            //Look at the player
+            let look_rotation = camera_transform.looking_at(pivot, Vec3::Y).rotation;
+            camera_transform.rotation = camera_transform.rotation.slerp(
+                look_rotation,
+                smoothing_factor(settings.rotation_half_life, delta_seconds),
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::scene::ScenePlugin;
+    use bevy_rapier3d::plugin::{NoUserData, RapierPhysicsPlugin};
+
+    use super::*;
+
+    #[test]
+    fn standing_on_the_floor_keeps_the_full_distance() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            AssetPlugin::default(),
+            ScenePlugin,
+        ))
+        .init_asset::<Mesh>()
+        .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+        // Snap straight to the target
+        .insert_resource(CameraSettings {
+            position_half_life: 0.0,
+            rotation_half_life: 0.0,
+            collision_recover_half_life: 0.0,
+            ..default()
+        })
+        .add_systems(Update, player_camera);
+
+        // Same floor as the level, its top at y = 0.1
+        app.world.spawn((
+            Collider::cuboid(50.0, 0.1, 50.0),
+            TransformBundle::default(),
+        ));
+        app.world.spawn((
+            PlayerTag,
+            TransformBundle::from_transform(Transform::from_xyz(0.0, 0.1, 0.0)),
+        ));
+        let orbit = OrbitCamera {
+            yaw: 0.0,
+            pitch: 10f32.to_radians(),
+            distance: 10.0,
+        };
+        let camera = app
+            .world
+            .spawn((
+                Camera3d::default(),
+                Transform::default(),
+                CameraCollision {
+                    distance: orbit.distance,
+                },
+                orbit,
+            ))
+            .id();
+
+        // Colliders reach the query pipeline after the first physics step
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let pivot = Vec3::new(0.0, 0.1 + CameraSettings::default().pivot_height, 0.0);
+        let camera_transform = app.world.get::<Transform>(camera).unwrap();
+        assert_eq!(
+            app.world.get::<CameraCollision>(camera).unwrap().distance,
+            10.0
+        );
+        assert!((camera_transform.translation.distance(pivot) - 10.0).abs() < 1e-4);
+    }
+}
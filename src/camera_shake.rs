@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+
+use crate::{camera::CameraFollow, states::gameplay_running};
+
+// Add trauma to the camera, from anything that should rattle the screen
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CameraShakeEvent {
+    // 0..1, added to the current trauma
+    pub trauma: f32,
+    // Where it happened, the trauma fades out with the distance to the camera
+    // None for things happening to the player itself
+    pub origin: Option<Vec3>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CameraShakeSettings {
+    // Trauma lost per second
+    pub decay: f32,
+    // Offsets reached at full trauma
+    pub max_translation: Vec3,
+    // Yaw, pitch and roll in radians
+    pub max_rotation: Vec3,
+    // Noise samples per second, higher is more jittery
+    pub frequency: f32,
+    // Positional events further than this from the camera are ignored
+    pub falloff_distance: f32,
+}
+
+impl Default for CameraShakeSettings {
+    fn default() -> Self {
+        Self {
+            decay: 0.8,
+            max_translation: Vec3::new(0.6, 0.4, 0.3),
+            max_rotation: Vec3::new(0.05, 0.05, 0.08),
+            frequency: 15.0,
+            falloff_distance: 40.0,
+        }
+    }
+}
+
+// Trauma of the camera and the offset applied on top of the follow transform this frame
+#[derive(Component, Debug, Default)]
+pub struct CameraShake {
+    pub trauma: f32,
+    applied_translation: Vec3,
+    applied_rotation: Quat,
+}
+
+pub struct CameraShakePlugin;
+
+impl Plugin for CameraShakePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraShakeSettings>()
+            .add_event::<CameraShakeEvent>()
+            .add_systems(
+                Update,
+                (
+                    (remove_camera_shake, add_camera_shake).before(CameraFollow),
+                    (add_trauma, apply_camera_shake).chain().after(CameraFollow),
+                )
+                    .run_if(gameplay_running),
+            );
+    }
+}
+
+fn add_camera_shake(mut commands: Commands, cameras: Query<Entity, Added<Camera3d>>) {
+    for camera in cameras.iter() {
+        commands.entity(camera).insert(CameraShake::default());
+    }
+}
+
+// Undo last frame's offset so the follow logic only sees its own transform
+fn remove_camera_shake(mut cameras: Query<(&mut Transform, &mut CameraShake)>) {
+    for (mut transform, mut shake) in cameras.iter_mut() {
+        transform.translation -= shake.applied_translation;
+        transform.rotation *= shake.applied_rotation.inverse();
+        shake.applied_translation = Vec3::ZERO;
+        shake.applied_rotation = Quat::IDENTITY;
+    }
+}
+
+fn add_trauma(
+    mut events: EventReader<CameraShakeEvent>,
+    mut cameras: Query<(&Transform, &mut CameraShake)>,
+    settings: Res<CameraShakeSettings>,
+) {
+    for event in events.read() {
+        for (transform, mut shake) in cameras.iter_mut() {
+            let falloff = event.origin.map_or(1.0, |origin| {
+                let distance = transform.translation.distance(origin);
+                (1.0 - distance / settings.falloff_distance).clamp(0.0, 1.0)
+            });
+            shake.trauma = (shake.trauma + event.trauma * falloff).clamp(0.0, 1.0);
+        }
+    }
+}
+
+fn apply_camera_shake(
+    mut cameras: Query<(&mut Transform, &mut CameraShake)>,
+    settings: Res<CameraShakeSettings>,
+    time: Res<Time>,
+) {
+    let t = time.elapsed_seconds() * settings.frequency;
+    for (mut transform, mut shake) in cameras.iter_mut() {
+        shake.trauma = (shake.trauma - settings.decay * time.delta_seconds()).max(0.0);
+        if shake.trauma == 0.0 {
+            continue;
+        }
+
+        // Squared so small hits stay subtle and big ones really kick
+        let amount = shake.trauma * shake.trauma;
+        let translation = transform.rotation
+            * (settings.max_translation
+                * amount
+                * Vec3::new(noise(0.0, t), noise(1.0, t), noise(2.0, t)));
+        let rotation = Quat::from_euler(
+            EulerRot::YXZ,
+            settings.max_rotation.x * amount * noise(3.0, t),
+            settings.max_rotation.y * amount * noise(4.0, t),
+            settings.max_rotation.z * amount * noise(5.0, t),
+        );
+
+        transform.translation += translation;
+        transform.rotation *= rotation;
+        shake.applied_translation = translation;
+        shake.applied_rotation = rotation;
+    }
+}
+
+// Smooth 1D value noise in -1..1, each seed gives an unrelated curve
+fn noise(seed: f32, t: f32) -> f32 {
+    let hash = |i: f32| {
+        let x = (i * 127.1 + seed * 311.7).sin() * 43758.547;
+        (x - x.floor()) * 2.0 - 1.0
+    };
+    let i = t.floor();
+    let f = t - i;
+    let smooth = f * f * (3.0 - 2.0 * f);
+    hash(i) + (hash(i + 1.0) - hash(i)) * smooth
+}
@@ -1,14 +1,26 @@
 use bevy::prelude::*;
 use bevy_rapier3d::{
-    control::{KinematicCharacterController, KinematicCharacterControllerOutput},
+    control::{
+        CharacterAutostep, CharacterLength, KinematicCharacterController,
+        KinematicCharacterControllerOutput,
+    },
     dynamics::RigidBody,
 };
 
 #[derive(Component)]
 pub struct NameComponent(pub String);
 
-#[derive(Component)]
-pub struct HealthComponent(pub f32);
+#[derive(Component, Debug)]
+pub struct HealthComponent {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl HealthComponent {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+}
 
 #[derive(Bundle)]
 pub struct CharacterPhysicsBody {
@@ -16,3 +28,24 @@ pub struct CharacterPhysicsBody {
     pub kinematic_controller: KinematicCharacterController,
     pub kinematic_controller_output: KinematicCharacterControllerOutput,
 }
+
+// Kinematic body moved through its controller, shared by the player and enemies
+impl Default for CharacterPhysicsBody {
+    fn default() -> Self {
+        Self {
+            rigid_body: RigidBody::KinematicPositionBased,
+            kinematic_controller: KinematicCharacterController {
+                offset: CharacterLength::Absolute(0.1),
+                apply_impulse_to_dynamic_bodies: true,
+                snap_to_ground: Some(CharacterLength::Absolute(10.)),
+                autostep: Some(CharacterAutostep {
+                    max_height: CharacterLength::Relative(1.),
+                    min_width: CharacterLength::Relative(0.6),
+                    include_dynamic_bodies: false,
+                }),
+                ..Default::default()
+            },
+            kinematic_controller_output: KinematicCharacterControllerOutput::default(),
+        }
+    }
+}
@@ -0,0 +1,391 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier3d::{geometry::Collider, pipeline::QueryFilter, plugin::RapierContext};
+use serde::Deserialize;
+
+use crate::{
+    asset_loader::AnimationEntityLink,
+    character::HealthComponent,
+    enemy::EnemyTag,
+    health::{DamageEvent, DamageType, Dead, HealthSet},
+    movable::{model_forward, HitReaction, Movable},
+    states::gameplay_running,
+};
+
+// Hit of a single melee attack
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct MeleeHit {
+    pub damage: f32,
+    // Seconds into the attack clip at which the hit is checked
+    pub hit_time: f32,
+    // Sphere checked for targets, centered `hit_distance` in front of the character
+    pub hit_radius: f32,
+    pub hit_distance: f32,
+    #[serde(default)]
+    pub knockback: f32,
+    // Seconds the game freezes when the hit connects, 0.0 for none
+    #[serde(default)]
+    pub hit_stop: f32,
+}
+
+// Melee attack of an AI character as written in its manifest
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct MeleeStats {
+    // Distance to the target from which the attack is started
+    pub range: f32,
+    // Seconds after an attack ends before the next one can start
+    pub cooldown: f32,
+    pub hit: MeleeHit,
+}
+
+#[derive(Component)]
+pub struct MeleeAttack {
+    pub stats: MeleeStats,
+    pub animation: Handle<AnimationClip>,
+    cooldown: Timer,
+}
+
+impl MeleeAttack {
+    pub fn new(stats: MeleeStats, animation: Handle<AnimationClip>) -> Self {
+        let mut cooldown = Timer::from_seconds(stats.cooldown, TimerMode::Once);
+        // Ready as soon as the character spawns
+        cooldown.tick(cooldown.duration());
+        Self {
+            stats,
+            animation,
+            cooldown,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.cooldown.finished()
+    }
+
+    pub fn attacking(&self) -> Attacking {
+        Attacking::new(self.stats.hit, self.animation.clone())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackInput {
+    Light,
+    Heavy,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ComboAttackStats {
+    // Inputs leading to this attack, [Light, Light, Heavy] is the third hit of that chain
+    pub chain: Vec<AttackInput>,
+    pub animation: String,
+    // Seconds into the clip from which the next attack of the chain can start
+    pub cancel_time: f32,
+    pub hit: MeleeHit,
+}
+
+// Light / heavy combo chains of a player character as written in its manifest
+#[derive(Deserialize, Debug, Clone)]
+pub struct ComboStats {
+    // Seconds a press is remembered while the current attack can't be cancelled yet
+    pub buffer_time: f32,
+    // Seconds after an attack ends during which the chain can still be continued
+    pub combo_window: f32,
+    pub attacks: Vec<ComboAttackStats>,
+}
+
+impl ComboStats {
+    fn find(&self, chain: &[AttackInput]) -> Option<usize> {
+        self.attacks.iter().position(|attack| attack.chain == chain)
+    }
+}
+
+#[derive(Component)]
+pub struct Combo {
+    stats: ComboStats,
+    // Same order as `stats.attacks`
+    animations: Vec<Handle<AnimationClip>>,
+    // Attack currently playing, or the last one while the combo window is open
+    current: Option<usize>,
+    buffered: Option<(AttackInput, f32)>,
+    window: f32,
+}
+
+impl Combo {
+    pub fn new(stats: ComboStats, animations: Vec<Handle<AnimationClip>>) -> Self {
+        Self {
+            stats,
+            animations,
+            current: None,
+            buffered: None,
+            window: 0.0,
+        }
+    }
+
+    // Attack continuing the chain with this input, or starting a new chain with it
+    fn next_attack(&self, input: AttackInput) -> Option<usize> {
+        if let Some(current) = self.current {
+            let mut chain = self.stats.attacks[current].chain.clone();
+            chain.push(input);
+            if let Some(next) = self.stats.find(&chain) {
+                return Some(next);
+            }
+        }
+        self.stats.find(&[input])
+    }
+}
+
+// Attack button pressed by a character with a Combo
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AttackInputEvent {
+    pub entity: Entity,
+    pub input: AttackInput,
+}
+
+// Inserted to start an attack, removed once the attack clip is over
+#[derive(Component, Debug)]
+pub struct Attacking {
+    pub hit: MeleeHit,
+    pub animation: Handle<AnimationClip>,
+    elapsed: f32,
+    started: bool,
+    hit_done: bool,
+}
+
+impl Attacking {
+    pub fn new(hit: MeleeHit, animation: Handle<AnimationClip>) -> Self {
+        Self {
+            hit,
+            animation,
+            elapsed: 0.0,
+            started: false,
+            hit_done: false,
+        }
+    }
+}
+
+// Freezes virtual time for a moment when a hit connects
+#[derive(Resource, Debug, Default)]
+pub struct HitStop {
+    remaining: f32,
+}
+
+impl HitStop {
+    pub fn trigger(&mut self, seconds: f32) {
+        self.remaining = self.remaining.max(seconds);
+    }
+}
+
+// Height of the hit sphere above the character's origin
+const HIT_HEIGHT: f32 = 1.0;
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HitStop>()
+            .add_event::<AttackInputEvent>()
+            // Not tied to a state so leaving Playing can't leave the game frozen
+            .add_systems(Update, update_hit_stop)
+            .add_systems(
+                Update,
+                (
+                    tick_attack_cooldowns,
+                    interrupt_attacks,
+                    buffer_attack_inputs,
+                    advance_combos,
+                    start_attacks,
+                    update_attacks,
+                )
+                    .chain()
+                    .after(update_hit_stop)
+                    .before(HealthSet)
+                    .run_if(gameplay_running),
+            );
+    }
+}
+
+fn update_hit_stop(
+    mut hit_stop: ResMut<HitStop>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+) {
+    if hit_stop.remaining > 0.0 {
+        // Counted in real time, virtual time is the one standing still
+        hit_stop.remaining -= real_time.delta_seconds();
+        virtual_time.set_relative_speed(if hit_stop.remaining > 0.0 { 0.0 } else { 1.0 });
+    }
+}
+
+fn tick_attack_cooldowns(mut attacks: Query<&mut MeleeAttack>, time: Res<Time>) {
+    for mut attack in attacks.iter_mut() {
+        attack.cooldown.tick(time.delta());
+    }
+}
+
+// Getting hit or dying cancels the attack before it lands
+fn interrupt_attacks(
+    mut commands: Commands,
+    mut attackers: Query<
+        (Entity, Option<&mut MeleeAttack>, Option<&mut Combo>),
+        (With<Attacking>, Or<(With<HitReaction>, With<Dead>)>),
+    >,
+) {
+    for (entity, attack, combo) in attackers.iter_mut() {
+        if let Some(mut attack) = attack {
+            attack.cooldown.reset();
+        }
+        if let Some(mut combo) = combo {
+            combo.current = None;
+            combo.buffered = None;
+        }
+        commands.entity(entity).remove::<Attacking>();
+    }
+}
+
+fn buffer_attack_inputs(
+    mut input_events: EventReader<AttackInputEvent>,
+    mut combos: Query<&mut Combo, (Without<Dead>, Without<HitReaction>)>,
+) {
+    for event in input_events.read() {
+        if let Ok(mut combo) = combos.get_mut(event.entity) {
+            combo.buffered = Some((event.input, combo.stats.buffer_time));
+        }
+    }
+}
+
+fn advance_combos(
+    mut commands: Commands,
+    mut combos: Query<(Entity, &mut Combo, Option<&Attacking>)>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (entity, mut combo, attacking) in combos.iter_mut() {
+        let can_start = match (attacking, combo.current) {
+            (Some(attacking), Some(current)) => {
+                attacking.elapsed >= combo.stats.attacks[current].cancel_time
+            }
+            (Some(_), None) => false,
+            (None, Some(_)) => {
+                // The attack is over, the chain can still be continued for a little while
+                if combo.window <= 0.0 {
+                    combo.window = combo.stats.combo_window;
+                }
+                combo.window -= delta_seconds;
+                if combo.window <= 0.0 {
+                    combo.current = None;
+                }
+                true
+            }
+            (None, None) => true,
+        };
+
+        let Some((input, remaining)) = combo.buffered else {
+            continue;
+        };
+        if !can_start {
+            // Too early, keep the press until the attack can be cancelled or the buffer expires
+            combo.buffered =
+                (remaining > delta_seconds).then(|| (input, remaining - delta_seconds));
+            continue;
+        }
+        combo.buffered = None;
+
+        let Some(next) = combo.next_attack(input) else {
+            continue;
+        };
+        combo.current = Some(next);
+        combo.window = 0.0;
+        commands.entity(entity).insert(Attacking::new(
+            combo.stats.attacks[next].hit,
+            combo.animations[next].clone(),
+        ));
+    }
+}
+
+fn start_attacks(
+    mut attackers: Query<(&mut Attacking, &mut Movable, Option<&AnimationEntityLink>)>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+) {
+    for (mut attacking, mut movable, target) in attackers.iter_mut() {
+        if attacking.started {
+            continue;
+        }
+        attacking.started = true;
+        movable.speed = 0.0;
+        movable.throttle = 0.0;
+
+        let Some(mut animator) = target.and_then(|target| animation_players.get_mut(target.0).ok())
+        else {
+            continue;
+        };
+        animator
+            .start_with_transition(attacking.animation.clone_weak(), Duration::from_millis(100))
+            .set_speed(1.0);
+    }
+}
+
+fn update_attacks(
+    mut commands: Commands,
+    mut attackers: Query<(Entity, &Transform, &mut Attacking, Option<&mut MeleeAttack>)>,
+    targets: Query<(), (With<HealthComponent>, Without<Dead>)>,
+    enemies: Query<(), With<EnemyTag>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut hit_stop: ResMut<HitStop>,
+    rapier_context: Res<RapierContext>,
+    clips: Res<Assets<AnimationClip>>,
+    time: Res<Time>,
+) {
+    for (entity, transform, mut attacking, attack) in attackers.iter_mut() {
+        if !attacking.started {
+            continue;
+        }
+        attacking.elapsed += time.delta_seconds();
+
+        let hit = attacking.hit;
+        if !attacking.hit_done && attacking.elapsed >= hit.hit_time {
+            attacking.hit_done = true;
+
+            let forward = model_forward(transform);
+            let center = transform.translation + forward * hit.hit_distance + Vec3::Y * HIT_HEIGHT;
+            let filter = QueryFilter::new()
+                .exclude_sensors()
+                .exclude_collider(entity)
+                .exclude_rigid_body(entity);
+            let attacker_is_enemy = enemies.contains(entity);
+            let mut connected = false;
+            rapier_context.intersections_with_shape(
+                center,
+                Quat::IDENTITY,
+                &Collider::ball(hit.hit_radius),
+                filter,
+                |target| {
+                    // No friendly fire between enemies
+                    if targets.contains(target) && enemies.contains(target) != attacker_is_enemy {
+                        connected = true;
+                        damage_events.send(DamageEvent {
+                            source: Some(entity),
+                            target,
+                            amount: hit.damage,
+                            damage_type: DamageType::Physical,
+                            knockback: forward * hit.knockback,
+                        });
+                    }
+                    true
+                },
+            );
+            if connected && hit.hit_stop > 0.0 {
+                hit_stop.trigger(hit.hit_stop);
+            }
+        }
+
+        let duration = clips
+            .get(&attacking.animation)
+            .map_or(hit.hit_time, |clip| clip.duration());
+        if attacking.elapsed >= duration {
+            if let Some(mut attack) = attack {
+                attack.cooldown.reset();
+            }
+            commands.entity(entity).remove::<Attacking>();
+        }
+    }
+}
@@ -5,103 +5,107 @@ use bevy_rapier3d::{
 };
 
 use crate::{
-    asset_loader::SkeletonSceneAssets,
-    character::{HealthComponent, NameComponent},
+    ai::EnemyAi,
+    asset_loader::{CharacterAsset, CharacterAssets, SpawnRole},
+    behavior::BehaviorRunner,
+    character::{CharacterPhysicsBody, HealthComponent, NameComponent},
+    health::DamageResistances,
     movable::{AnimatedCharacterMovable, Movable},
-    player::PlayerTag,
+    navigation::NavPath,
+    perception::Perception,
+    states::{GameState, StateScoped},
 };
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PostStartup, spawn_enemy)
-            .add_systems(Update, execute_ai);
+        app.add_systems(OnEnter(GameState::Playing), spawn_enemy);
     }
 }
 
 #[derive(Component)]
 pub struct EnemyTag;
 
-#[derive(Component, Default)]
-pub enum AiType {
-    #[default]
-    FOLLOW,
-    NONE,
-}
+// Capsule collider of the enemies, raised so its bottom sits at the model's feet
+const COLLIDER_HALF_HEIGHT: f32 = 0.5;
+const COLLIDER_RADIUS: f32 = 0.6;
 
 #[derive(Bundle)]
 pub struct EnemyBundle {
+    pub character_physics_body: CharacterPhysicsBody,
+    pub collider: Collider,
     pub model: SceneBundle,
     pub name: NameComponent,
     pub health: HealthComponent,
+    pub resistances: DamageResistances,
     pub tag: EnemyTag,
     pub movable: Movable,
     pub movable_animation: AnimatedCharacterMovable,
-    pub ai_type: AiType,
+    pub nav_path: NavPath,
+    pub perception: Perception,
 }
 
-fn spawn_enemy(mut commands: Commands, asset_server: ResMut<SkeletonSceneAssets>) {
-    commands.spawn(EnemyBundle {
-        model: SceneBundle {
-            scene: asset_server.skeleton.clone(),
-            transform: Transform::from_translation(Vec3 {
-                x: 4.,
-                y: 0.0,
-                z: 4.,
-            }),
-            ..Default::default()
-        },
-        name: NameComponent("Evil boy".to_string()),
-        health: HealthComponent(100.0),
-        tag: EnemyTag,
-        movable: Movable {
-            max_speed: 7.0,
-            max_acceleration: 20.0,
-            ..Default::default()
-        },
-        movable_animation: AnimatedCharacterMovable {
-            run_animation: asset_server.skeleton_run_animation.clone(),
-            walk_animation: asset_server.skeleton_walk_animation.clone(),
-            idle_animations: asset_server.skeleton_idle_animations.clone(),
-        },
-        ai_type: AiType::FOLLOW,
-    });
+fn spawn_enemy(
+    mut commands: Commands,
+    character_assets: Res<CharacterAssets>,
+    characters: Res<Assets<CharacterAsset>>,
+) {
+    for character in character_assets.with_role(SpawnRole::Enemy, &characters) {
+        let Some(spawn) = &character.spawn else {
+            continue;
+        };
+        for (name, translation) in spawn.spawn_points(&character.name) {
+            spawn_enemy_character(&mut commands, character, name, translation);
+        }
+    }
 }
 
-fn execute_ai(
-    mut enemies: Query<(&mut Movable, &mut Transform, &AiType), With<EnemyTag>>,
-    player: Query<&Transform, (With<PlayerTag>, Without<EnemyTag>)>,
+fn spawn_enemy_character(
+    commands: &mut Commands,
+    character: &CharacterAsset,
+    name: String,
+    translation: Vec3,
 ) {
-    let target = player.get_single();
-    if let Ok(player_transform) = target {
-        for (mut movable, mut enemy_transform, ai_type) in enemies.iter_mut() {
-            match ai_type {
-                AiType::FOLLOW => {
-                    let r_pos = player_transform.translation - enemy_transform.translation;
-                    let target = enemy_transform.translation - r_pos;
-                    let look_at_target =
-                        Vec3::new(target.x, enemy_transform.translation.y, target.z);
-                    enemy_transform.look_at(look_at_target, Vec3::Y);
-
-                    let distance = enemy_transform
-                        .translation
-                        .distance(player_transform.translation);
+    let mut enemy = commands.spawn(enemy_bundle(character, name, translation));
+    enemy.insert(StateScoped(GameState::Playing));
+    // A behavior tree from the manifest takes over from the built-in state machine
+    match &character.behavior {
+        Some(tree) => enemy.insert(BehaviorRunner::new(
+            tree.clone(),
+            character.gltf.clone(),
+            translation,
+        )),
+        None => enemy.insert(EnemyAi::new(translation, character.ai)),
+    };
+    if let Some(node) = character.animation_player_node() {
+        enemy.insert(node);
+    }
+    if let Some(melee_attack) = character.melee_attack() {
+        enemy.insert(melee_attack);
+    }
+}
 
-                    if distance > 7.0 {
-                        if distance > 18.0 {
-                            movable.acceleration = 10.0;
-                            movable.fast = false;
-                        } else {
-                            movable.fast = true;
-                            movable.acceleration = 15.0;
-                        }
-                    } else {
-                        movable.acceleration = -(movable.speed);
-                    }
-                }
-                AiType::NONE => {}
-            }
-        }
+fn enemy_bundle(character: &CharacterAsset, name: String, translation: Vec3) -> EnemyBundle {
+    EnemyBundle {
+        character_physics_body: CharacterPhysicsBody::default(),
+        collider: Collider::compound(vec![(
+            Vec3::Y * (COLLIDER_HALF_HEIGHT + COLLIDER_RADIUS),
+            Quat::IDENTITY,
+            Collider::capsule_y(COLLIDER_HALF_HEIGHT, COLLIDER_RADIUS),
+        )]),
+        model: SceneBundle {
+            scene: character.scene.clone(),
+            transform: Transform::from_translation(translation),
+            ..Default::default()
+        },
+        name: NameComponent(name),
+        health: character.health(),
+        resistances: character.resistances.clone(),
+        tag: EnemyTag,
+        movable: character.movable(),
+        movable_animation: character.animated_movable(),
+        nav_path: NavPath::default(),
+        perception: character.perception(),
     }
 }
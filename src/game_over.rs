@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use crate::{
+    enemy::EnemyTag,
+    health::DeathEvent,
+    menu::{menu_root, spawn_menu_button, MenuAction, MenuActionEvent},
+    player::PlayerTag,
+    states::{gameplay_running, GameState, StateScoped},
+};
+
+// Seconds between the player's death and the game over screen, time for the death animation
+const GAME_OVER_DELAY: f32 = 2.5;
+
+// Stats of the current run, reset every time Playing is entered
+#[derive(Resource, Debug, Default)]
+pub struct RunStats {
+    pub time_survived: f32,
+    pub enemies_killed: u32,
+}
+
+// Running from the player's death until the game over screen shows up
+#[derive(Resource)]
+struct GameOverDelay(Timer);
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_systems(OnEnter(GameState::Playing), reset_run_stats)
+            .add_systems(
+                Update,
+                (
+                    update_run_stats,
+                    start_game_over_delay,
+                    finish_game_over_delay,
+                )
+                    .chain()
+                    .run_if(gameplay_running),
+            )
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen)
+            .add_systems(Update, restart.run_if(in_state(GameState::GameOver)));
+    }
+}
+
+fn reset_run_stats(mut commands: Commands) {
+    commands.insert_resource(RunStats::default());
+    commands.remove_resource::<GameOverDelay>();
+}
+
+fn update_run_stats(
+    mut stats: ResMut<RunStats>,
+    mut death_events: EventReader<DeathEvent>,
+    enemies: Query<(), With<EnemyTag>>,
+    player: Query<(), With<PlayerTag>>,
+    delay: Option<Res<GameOverDelay>>,
+    time: Res<Time>,
+) {
+    // The clock stops with the player's death, not once the screen shows up
+    if delay.is_none() && !player.is_empty() {
+        stats.time_survived += time.delta_seconds();
+    }
+    for event in death_events.read() {
+        // Enemies only count when the player killed them
+        let by_player = event.killer.is_some_and(|killer| player.contains(killer));
+        if enemies.contains(event.entity) && by_player {
+            stats.enemies_killed += 1;
+        }
+    }
+}
+
+fn start_game_over_delay(
+    mut commands: Commands,
+    mut death_events: EventReader<DeathEvent>,
+    player: Query<(), With<PlayerTag>>,
+) {
+    for event in death_events.read() {
+        if player.contains(event.entity) {
+            commands.insert_resource(GameOverDelay(Timer::from_seconds(
+                GAME_OVER_DELAY,
+                TimerMode::Once,
+            )));
+        }
+    }
+}
+
+fn finish_game_over_delay(
+    delay: Option<ResMut<GameOverDelay>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    let Some(mut delay) = delay else {
+        return;
+    };
+    if delay.0.tick(time.delta()).just_finished() {
+        game_state.set(GameState::GameOver);
+    }
+}
+
+fn spawn_game_over_screen(mut commands: Commands, stats: Res<RunStats>) {
+    let seconds = stats.time_survived as u32;
+    let text_style = |font_size| TextStyle {
+        font_size,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    // The gameplay camera went away with the rest of the Playing entities
+    commands.spawn((Camera2dBundle::default(), StateScoped(GameState::GameOver)));
+    commands
+        .spawn((menu_root(), StateScoped(GameState::GameOver)))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Game over",
+                TextStyle {
+                    font_size: 60.0,
+                    color: Color::rgb(1.0, 0.3, 0.3),
+                    ..default()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Time survived: {}:{:02}", seconds / 60, seconds % 60),
+                text_style(24.0),
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Enemies killed: {}", stats.enemies_killed),
+                text_style(24.0),
+            ));
+            spawn_menu_button(parent, "Restart", MenuAction::Restart);
+            spawn_menu_button(parent, "Quit", MenuAction::Quit);
+        });
+}
+
+// Assets stay loaded, entering Playing again respawns everything from them
+fn restart(
+    mut action_events: EventReader<MenuActionEvent>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let pressed = action_events
+        .read()
+        .any(|event| event.0 == MenuAction::Restart);
+    if pressed || keyboard_input.just_pressed(KeyCode::R) {
+        game_state.set(GameState::Playing);
+    }
+}
@@ -0,0 +1,375 @@
+use std::{fs, path::Path};
+
+use bevy::{
+    core_pipeline::{
+        bloom::BloomSettings,
+        experimental::taa::{TemporalAntiAliasBundle, TemporalAntiAliasSettings},
+        prepass::{DepthPrepass, MotionVectorPrepass, NormalPrepass},
+        tonemapping::Tonemapping,
+    },
+    pbr::{
+        ScreenSpaceAmbientOcclusionBundle, ScreenSpaceAmbientOcclusionQualityLevel,
+        ScreenSpaceAmbientOcclusionSettings,
+    },
+    prelude::*,
+    render::camera::TemporalJitter,
+};
+use serde::{Deserialize, Serialize};
+
+// Written next to the executable's working directory, not inside assets/
+const GRAPHICS_SETTINGS_PATH: &str = "settings/graphics.ron";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsPreset {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl GraphicsPreset {
+    pub fn next(self) -> Self {
+        match self {
+            GraphicsPreset::Low => GraphicsPreset::Medium,
+            GraphicsPreset::Medium => GraphicsPreset::High,
+            GraphicsPreset::High => GraphicsPreset::Ultra,
+            GraphicsPreset::Ultra => GraphicsPreset::Low,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SsaoQuality {
+    Off,
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntiAliasing {
+    None,
+    Msaa,
+    Taa,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemapper {
+    None,
+    Reinhard,
+    AcesFitted,
+    AgX,
+    TonyMcMapface,
+    BlenderFilmic,
+}
+
+impl From<Tonemapper> for Tonemapping {
+    fn from(tonemapper: Tonemapper) -> Self {
+        match tonemapper {
+            Tonemapper::None => Tonemapping::None,
+            Tonemapper::Reinhard => Tonemapping::Reinhard,
+            Tonemapper::AcesFitted => Tonemapping::AcesFitted,
+            Tonemapper::AgX => Tonemapping::AgX,
+            Tonemapper::TonyMcMapface => Tonemapping::TonyMcMapface,
+            Tonemapper::BlenderFilmic => Tonemapping::BlenderFilmic,
+        }
+    }
+}
+
+// One entry of the settings menu, changed by cycling through its values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsOption {
+    // Replaces every option below
+    Preset,
+    Bloom,
+    Ssao,
+    AntiAliasing,
+    Hdr,
+    Tonemapper,
+    Shadows,
+}
+
+impl GraphicsOption {
+    pub const ALL: [GraphicsOption; 7] = [
+        GraphicsOption::Preset,
+        GraphicsOption::Bloom,
+        GraphicsOption::Ssao,
+        GraphicsOption::AntiAliasing,
+        GraphicsOption::Hdr,
+        GraphicsOption::Tonemapper,
+        GraphicsOption::Shadows,
+    ];
+
+    // Shown on the settings menu button, with the current value
+    pub fn label(self, settings: &GraphicsSettings) -> String {
+        let on_off = |enabled| if enabled { "On" } else { "Off" };
+        match self {
+            GraphicsOption::Preset => format!("Preset: {:?}", settings.preset),
+            GraphicsOption::Bloom => match settings.bloom_intensity {
+                intensity if intensity <= 0.0 => "Bloom: Off".to_string(),
+                intensity => format!("Bloom: {intensity:.2}"),
+            },
+            GraphicsOption::Ssao => format!("SSAO: {:?}", settings.ssao),
+            GraphicsOption::AntiAliasing => {
+                format!("Anti-aliasing: {:?}", settings.anti_aliasing)
+            }
+            GraphicsOption::Hdr => format!("HDR: {}", on_off(settings.hdr)),
+            GraphicsOption::Tonemapper => format!("Tonemapping: {:?}", settings.tonemapper),
+            GraphicsOption::Shadows => format!("Shadows: {}", on_off(settings.shadows)),
+        }
+    }
+}
+
+// Values the bloom option cycles through
+const BLOOM_INTENSITIES: [f32; 3] = [0.0, 0.15, BloomSettings::NATURAL.intensity];
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GraphicsSettings {
+    // Last preset applied, the toggles below can be changed on top of it
+    pub preset: GraphicsPreset,
+    // 0.0 disables bloom
+    pub bloom_intensity: f32,
+    pub ssao: SsaoQuality,
+    pub anti_aliasing: AntiAliasing,
+    pub hdr: bool,
+    pub tonemapper: Tonemapper,
+    pub shadows: bool,
+}
+
+impl GraphicsSettings {
+    pub fn from_preset(preset: GraphicsPreset) -> Self {
+        match preset {
+            GraphicsPreset::Low => Self {
+                preset,
+                bloom_intensity: 0.0,
+                ssao: SsaoQuality::Off,
+                anti_aliasing: AntiAliasing::None,
+                hdr: false,
+                tonemapper: Tonemapper::Reinhard,
+                shadows: false,
+            },
+            GraphicsPreset::Medium => Self {
+                preset,
+                bloom_intensity: 0.15,
+                ssao: SsaoQuality::Off,
+                anti_aliasing: AntiAliasing::Msaa,
+                hdr: false,
+                tonemapper: Tonemapper::TonyMcMapface,
+                shadows: true,
+            },
+            GraphicsPreset::High => Self {
+                preset,
+                bloom_intensity: BloomSettings::NATURAL.intensity,
+                ssao: SsaoQuality::High,
+                anti_aliasing: AntiAliasing::Taa,
+                hdr: false,
+                tonemapper: Tonemapper::TonyMcMapface,
+                shadows: true,
+            },
+            GraphicsPreset::Ultra => Self {
+                preset,
+                bloom_intensity: BloomSettings::NATURAL.intensity,
+                ssao: SsaoQuality::Ultra,
+                anti_aliasing: AntiAliasing::Taa,
+                hdr: true,
+                tonemapper: Tonemapper::TonyMcMapface,
+                shadows: true,
+            },
+        }
+    }
+
+    // Next value of one option, the preset is kept as the base the toggles were changed on
+    pub fn cycle(&mut self, option: GraphicsOption) {
+        match option {
+            GraphicsOption::Preset => *self = Self::from_preset(self.preset.next()),
+            GraphicsOption::Bloom => {
+                self.bloom_intensity = BLOOM_INTENSITIES
+                    .into_iter()
+                    .find(|intensity| *intensity > self.bloom_intensity + 1e-3)
+                    .unwrap_or(0.0);
+            }
+            GraphicsOption::Ssao => {
+                self.ssao = match self.ssao {
+                    SsaoQuality::Off => SsaoQuality::Low,
+                    SsaoQuality::Low => SsaoQuality::Medium,
+                    SsaoQuality::Medium => SsaoQuality::High,
+                    SsaoQuality::High => SsaoQuality::Ultra,
+                    SsaoQuality::Ultra => SsaoQuality::Off,
+                }
+            }
+            GraphicsOption::AntiAliasing => {
+                self.anti_aliasing = match self.anti_aliasing {
+                    AntiAliasing::None => AntiAliasing::Msaa,
+                    AntiAliasing::Msaa => AntiAliasing::Taa,
+                    AntiAliasing::Taa => AntiAliasing::None,
+                }
+            }
+            GraphicsOption::Hdr => self.hdr = !self.hdr,
+            GraphicsOption::Tonemapper => {
+                self.tonemapper = match self.tonemapper {
+                    Tonemapper::None => Tonemapper::Reinhard,
+                    Tonemapper::Reinhard => Tonemapper::AcesFitted,
+                    Tonemapper::AcesFitted => Tonemapper::AgX,
+                    Tonemapper::AgX => Tonemapper::TonyMcMapface,
+                    Tonemapper::TonyMcMapface => Tonemapper::BlenderFilmic,
+                    Tonemapper::BlenderFilmic => Tonemapper::None,
+                }
+            }
+            GraphicsOption::Shadows => self.shadows = !self.shadows,
+        }
+    }
+
+    pub fn load() -> Self {
+        let Ok(content) = fs::read_to_string(GRAPHICS_SETTINGS_PATH) else {
+            return Self::default();
+        };
+        match ron::from_str(&content) {
+            Ok(settings) => settings,
+            Err(error) => {
+                warn!("Ignoring invalid {GRAPHICS_SETTINGS_PATH}: {error}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let content = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Could not serialize graphics settings: {error}");
+                return;
+            }
+        };
+        let path = Path::new(GRAPHICS_SETTINGS_PATH);
+        if let Some(folder) = path.parent() {
+            let _ = fs::create_dir_all(folder);
+        }
+        if let Err(error) = fs::write(path, content) {
+            error!("Could not write {GRAPHICS_SETTINGS_PATH}: {error}");
+        }
+    }
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self::from_preset(GraphicsPreset::Ultra)
+    }
+}
+
+pub struct GraphicsPlugin;
+
+impl Plugin for GraphicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GraphicsSettings::load()).add_systems(
+            Update,
+            (
+                cycle_graphics_preset,
+                apply_camera_settings,
+                apply_light_settings,
+                save_graphics_settings,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn cycle_graphics_preset(
+    mut settings: ResMut<GraphicsSettings>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        settings.cycle(GraphicsOption::Preset);
+        info!("Graphics preset: {:?}", settings.preset);
+    }
+}
+
+fn apply_camera_settings(
+    mut commands: Commands,
+    settings: Res<GraphicsSettings>,
+    mut msaa: ResMut<Msaa>,
+    mut cameras: Query<(Entity, &mut Camera, Ref<Camera3d>)>,
+) {
+    // SSAO and TAA both need MSAA off
+    if settings.is_changed() {
+        let wanted_msaa = match settings.anti_aliasing {
+            AntiAliasing::Msaa if settings.ssao != SsaoQuality::Off => {
+                warn!("MSAA is not supported together with SSAO, disabling MSAA");
+                Msaa::Off
+            }
+            AntiAliasing::Msaa => Msaa::Sample4,
+            AntiAliasing::None | AntiAliasing::Taa => Msaa::Off,
+        };
+        if *msaa != wanted_msaa {
+            *msaa = wanted_msaa;
+        }
+    }
+
+    for (entity, mut camera, camera_3d) in cameras.iter_mut() {
+        if !settings.is_changed() && !camera_3d.is_added() {
+            continue;
+        }
+        camera.hdr = settings.hdr;
+
+        let mut camera_commands = commands.entity(entity);
+        camera_commands.insert(Tonemapping::from(settings.tonemapper));
+
+        if settings.bloom_intensity > 0.0 {
+            camera_commands.insert(BloomSettings {
+                intensity: settings.bloom_intensity,
+                ..BloomSettings::NATURAL
+            });
+        } else {
+            camera_commands.remove::<BloomSettings>();
+        }
+
+        let quality_level = match settings.ssao {
+            SsaoQuality::Off => None,
+            SsaoQuality::Low => Some(ScreenSpaceAmbientOcclusionQualityLevel::Low),
+            SsaoQuality::Medium => Some(ScreenSpaceAmbientOcclusionQualityLevel::Medium),
+            SsaoQuality::High => Some(ScreenSpaceAmbientOcclusionQualityLevel::High),
+            SsaoQuality::Ultra => Some(ScreenSpaceAmbientOcclusionQualityLevel::Ultra),
+        };
+        match quality_level {
+            Some(quality_level) => {
+                camera_commands.insert(ScreenSpaceAmbientOcclusionBundle {
+                    settings: ScreenSpaceAmbientOcclusionSettings { quality_level },
+                    ..Default::default()
+                });
+            }
+            None => {
+                camera_commands.remove::<(ScreenSpaceAmbientOcclusionSettings, NormalPrepass)>();
+            }
+        }
+
+        if settings.anti_aliasing == AntiAliasing::Taa {
+            camera_commands.insert(TemporalAntiAliasBundle::default());
+        } else {
+            camera_commands.remove::<(
+                TemporalAntiAliasSettings,
+                TemporalJitter,
+                MotionVectorPrepass,
+            )>();
+        }
+
+        // The depth prepass is shared by SSAO and TAA
+        if quality_level.is_none() && settings.anti_aliasing != AntiAliasing::Taa {
+            camera_commands.remove::<DepthPrepass>();
+        }
+    }
+}
+
+fn apply_light_settings(settings: Res<GraphicsSettings>, mut lights: Query<&mut PointLight>) {
+    for mut light in lights.iter_mut() {
+        if settings.is_changed() || light.is_added() {
+            light.shadows_enabled = settings.shadows;
+        }
+    }
+}
+
+fn save_graphics_settings(settings: Res<GraphicsSettings>) {
+    // Skip the first run, the settings were just loaded
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
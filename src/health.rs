@@ -0,0 +1,301 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+
+use crate::character::HealthComponent;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageType {
+    Physical,
+    Fall,
+    Explosion,
+}
+
+// Fraction of each damage type ignored by a character, 0.0 (none) to 1.0 (immune)
+#[derive(Component, Deserialize, Debug, Clone, Default)]
+pub struct DamageResistances(pub HashMap<DamageType, f32>);
+
+impl DamageResistances {
+    pub fn multiplier(&self, damage_type: DamageType) -> f32 {
+        1.0 - self
+            .0
+            .get(&damage_type)
+            .copied()
+            .unwrap_or(0.0)
+            .clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: f32,
+    pub damage_type: DamageType,
+    // Velocity pushed onto the target, zero for none
+    pub knockback: Vec3,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HealEvent {
+    pub target: Entity,
+    pub amount: f32,
+}
+
+// Sent for every DamageEvent that actually hurt a living character, after resistances
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageTakenEvent {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: f32,
+}
+
+// Sent exactly once, the frame a character's health reaches zero
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub killer: Option<Entity>,
+}
+
+#[derive(Component, Debug)]
+pub struct Dead;
+
+// Velocity added on top of the character's own movement, fades out over time
+#[derive(Component, Debug, Default)]
+pub struct Knockback(pub Vec3);
+
+// Health changes happen in this set, read health or the events above after it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HealthSet;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<HealEvent>()
+            .add_event::<DamageTakenEvent>()
+            .add_event::<DeathEvent>()
+            .add_systems(
+                Update,
+                (apply_damage, apply_healing).chain().in_set(HealthSet),
+            );
+    }
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut damage_taken_events: EventWriter<DamageTakenEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut targets: Query<
+        (
+            &mut HealthComponent,
+            Option<&DamageResistances>,
+            Option<&mut Knockback>,
+        ),
+        Without<Dead>,
+    >,
+) {
+    for event in damage_events.read() {
+        let Ok((mut health, resistances, knockback)) = targets.get_mut(event.target) else {
+            continue;
+        };
+        // Already killed earlier this frame, Dead is only inserted at the end of it
+        if health.current <= 0.0 {
+            continue;
+        }
+
+        let amount = event.amount
+            * resistances.map_or(1.0, |resistances| resistances.multiplier(event.damage_type));
+        // Fully resisted, or negative damage which would heal
+        if amount <= 0.0 {
+            continue;
+        }
+        health.current = (health.current - amount).clamp(0.0, health.max);
+
+        if event.knockback != Vec3::ZERO {
+            match knockback {
+                Some(mut knockback) => knockback.0 += event.knockback,
+                None => {
+                    commands
+                        .entity(event.target)
+                        .insert(Knockback(event.knockback));
+                }
+            }
+        }
+
+        damage_taken_events.send(DamageTakenEvent {
+            source: event.source,
+            target: event.target,
+            amount,
+        });
+
+        if health.current <= 0.0 {
+            commands.entity(event.target).insert(Dead);
+            death_events.send(DeathEvent {
+                entity: event.target,
+                killer: event.source,
+            });
+        }
+    }
+}
+
+fn apply_healing(
+    mut heal_events: EventReader<HealEvent>,
+    mut targets: Query<&mut HealthComponent, Without<Dead>>,
+) {
+    for event in heal_events.read() {
+        let Ok(mut health) = targets.get_mut(event.target) else {
+            continue;
+        };
+        if health.current <= 0.0 || event.amount <= 0.0 {
+            continue;
+        }
+        health.current = (health.current + event.amount).min(health.max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(HealthPlugin);
+        app
+    }
+
+    fn damage(app: &mut App, target: Entity, amount: f32, damage_type: DamageType) {
+        app.world.send_event(DamageEvent {
+            source: None,
+            target,
+            amount,
+            damage_type,
+            knockback: Vec3::ZERO,
+        });
+    }
+
+    fn taken_events(app: &App) -> Vec<DamageTakenEvent> {
+        let events = app.world.resource::<Events<DamageTakenEvent>>();
+        events.get_reader().read(events).copied().collect()
+    }
+
+    fn death_count(app: &App) -> usize {
+        let events = app.world.resource::<Events<DeathEvent>>();
+        events.get_reader().read(events).count()
+    }
+
+    #[test]
+    fn resistances_reduce_damage() {
+        let mut app = app();
+        let target = app
+            .world
+            .spawn((
+                HealthComponent::new(100.0),
+                DamageResistances(HashMap::from([
+                    (DamageType::Physical, 0.25),
+                    (DamageType::Fall, 1.0),
+                ])),
+            ))
+            .id();
+
+        damage(&mut app, target, 20.0, DamageType::Physical);
+        damage(&mut app, target, 50.0, DamageType::Fall);
+        app.update();
+
+        assert_eq!(
+            app.world.get::<HealthComponent>(target).unwrap().current,
+            85.0
+        );
+        // The fully resisted hit is not reported
+        let taken = taken_events(&app);
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].amount, 15.0);
+    }
+
+    #[test]
+    fn negative_damage_does_not_heal() {
+        let mut app = app();
+        let target = app.world.spawn(HealthComponent::new(100.0)).id();
+
+        damage(&mut app, target, -50.0, DamageType::Physical);
+        app.update();
+
+        assert_eq!(
+            app.world.get::<HealthComponent>(target).unwrap().current,
+            100.0
+        );
+        assert!(taken_events(&app).is_empty());
+    }
+
+    #[test]
+    fn overkill_stops_at_zero() {
+        let mut app = app();
+        let target = app.world.spawn(HealthComponent::new(30.0)).id();
+
+        damage(&mut app, target, 500.0, DamageType::Explosion);
+        app.update();
+
+        assert_eq!(
+            app.world.get::<HealthComponent>(target).unwrap().current,
+            0.0
+        );
+        assert!(app.world.get::<Dead>(target).is_some());
+    }
+
+    #[test]
+    fn dies_exactly_once() {
+        let mut app = app();
+        let target = app.world.spawn(HealthComponent::new(30.0)).id();
+
+        // Two lethal hits in the same frame, then another one once dead
+        damage(&mut app, target, 40.0, DamageType::Physical);
+        damage(&mut app, target, 40.0, DamageType::Physical);
+        app.update();
+        assert_eq!(death_count(&app), 1);
+        assert_eq!(taken_events(&app).len(), 1);
+
+        damage(&mut app, target, 40.0, DamageType::Physical);
+        app.update();
+        // Events live for two updates, the first death is still readable
+        assert_eq!(death_count(&app), 1);
+    }
+
+    #[test]
+    fn healing_stops_at_max_and_skips_the_dead() {
+        let mut app = app();
+        let hurt = app
+            .world
+            .spawn(HealthComponent {
+                current: 90.0,
+                max: 100.0,
+            })
+            .id();
+        let dead = app
+            .world
+            .spawn((
+                HealthComponent {
+                    current: 0.0,
+                    max: 100.0,
+                },
+                Dead,
+            ))
+            .id();
+
+        app.world.send_event(HealEvent {
+            target: hurt,
+            amount: 50.0,
+        });
+        app.world.send_event(HealEvent {
+            target: dead,
+            amount: 50.0,
+        });
+        app.update();
+
+        assert_eq!(
+            app.world.get::<HealthComponent>(hurt).unwrap().current,
+            100.0
+        );
+        assert_eq!(app.world.get::<HealthComponent>(dead).unwrap().current, 0.0);
+    }
+}
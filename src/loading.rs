@@ -0,0 +1,231 @@
+use bevy::{
+    asset::{LoadState, UntypedAssetId},
+    prelude::*,
+    utils::HashSet,
+};
+
+use crate::states::{GameState, StateScoped};
+
+// Systems registering handles or reporting failures run in this set,
+// before the tracker decides whether loading is over
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrackLoading;
+
+#[derive(Debug, Clone)]
+pub struct LoadingFailure {
+    pub path: String,
+    pub reason: String,
+}
+
+// Every handle the game needs before leaving GameState::Loading
+#[derive(Resource, Debug, Default)]
+pub struct LoadingTracker {
+    handles: Vec<UntypedHandle>,
+    tracked: HashSet<UntypedAssetId>,
+    loaded: usize,
+    // Highest fraction shown so far, handles found later would make the bar jump back
+    progress: f32,
+    failure: Option<LoadingFailure>,
+}
+
+impl LoadingTracker {
+    pub fn track(&mut self, handle: UntypedHandle) {
+        if self.tracked.insert(handle.id()) {
+            self.handles.push(handle);
+        }
+    }
+
+    pub fn fail(&mut self, path: impl Into<String>, reason: impl Into<String>) {
+        if self.failure.is_none() {
+            self.failure = Some(LoadingFailure {
+                path: path.into(),
+                reason: reason.into(),
+            });
+        }
+    }
+
+    fn set_loaded(&mut self, loaded: usize) {
+        self.loaded = loaded;
+        if !self.handles.is_empty() {
+            let fraction = loaded as f32 / self.handles.len() as f32;
+            self.progress = self.progress.max(fraction);
+        }
+    }
+
+    // Fraction of tracked handles loaded with their dependencies, 0..1, never going back
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    pub fn is_done(&self) -> bool {
+        !self.handles.is_empty() && self.loaded == self.handles.len()
+    }
+
+    pub fn failure(&self) -> Option<&LoadingFailure> {
+        self.failure.as_ref()
+    }
+}
+
+// Copied out of the tracker when entering GameState::LoadingError
+#[derive(Resource, Debug)]
+pub struct LoadingError(pub LoadingFailure);
+
+#[derive(Component)]
+struct LoadingProgressBar;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadingTracker>()
+            .add_systems(OnEnter(GameState::Loading), spawn_loading_screen)
+            .add_systems(
+                Update,
+                (update_loading_progress, finish_loading, update_progress_bar)
+                    .chain()
+                    .after(TrackLoading)
+                    .run_if(in_state(GameState::Loading)),
+            )
+            .add_systems(OnEnter(GameState::LoadingError), spawn_loading_error_screen);
+    }
+}
+
+fn update_loading_progress(mut tracker: ResMut<LoadingTracker>, asset_server: Res<AssetServer>) {
+    let mut loaded = 0;
+    let mut failed = None;
+    for handle in tracker.handles.iter() {
+        if asset_server.load_state(handle.id()) == LoadState::Failed {
+            failed = Some(handle.id());
+        } else if asset_server.is_loaded_with_dependencies(handle.id()) {
+            loaded += 1;
+        }
+    }
+    tracker.set_loaded(loaded);
+
+    if let Some(id) = failed {
+        let path = asset_server
+            .get_path(id)
+            .map(|path| path.to_string())
+            .unwrap_or_else(|| format!("{id:?}"));
+        tracker.fail(path, "asset failed to load");
+    }
+}
+
+fn finish_loading(
+    mut commands: Commands,
+    mut game_state: ResMut<NextState<GameState>>,
+    tracker: Res<LoadingTracker>,
+) {
+    if let Some(failure) = tracker.failure() {
+        error!("Failed to load {}: {}", failure.path, failure.reason);
+        commands.insert_resource(LoadingError(failure.clone()));
+        game_state.set(GameState::LoadingError);
+    } else if tracker.is_done() {
+        info!("Loaded, Show main menu");
+        game_state.set(GameState::MainMenu);
+    }
+}
+
+fn update_progress_bar(
+    tracker: Res<LoadingTracker>,
+    mut bars: Query<&mut Style, With<LoadingProgressBar>>,
+) {
+    for mut style in bars.iter_mut() {
+        style.width = Val::Percent(tracker.progress() * 100.0);
+    }
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), StateScoped(GameState::Loading)));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(16.0),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(GameState::Loading),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Loading",
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(50.0),
+                        height: Val::Px(20.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.4, 0.3, 0.9).into(),
+                            ..default()
+                        },
+                        LoadingProgressBar,
+                    ));
+                });
+        });
+}
+
+fn spawn_loading_error_screen(mut commands: Commands, error: Res<LoadingError>) {
+    commands.spawn((
+        Camera2dBundle::default(),
+        StateScoped(GameState::LoadingError),
+    ));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(16.0),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(GameState::LoadingError),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Failed to load assets",
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::rgb(1.0, 0.3, 0.3),
+                    ..default()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("{}: {}", error.0.path, error.0.reason),
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
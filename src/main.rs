@@ -1,14 +1,31 @@
+mod ai;
 mod asset_loader;
+mod behavior;
 mod camera;
+mod camera_shake;
 mod character;
+mod combat;
 mod enemy;
+mod game_over;
+mod graphics;
+mod health;
+mod loading;
+mod main_menu;
+mod menu;
 mod movable;
+mod navigation;
+mod pause;
+mod perception;
 mod player;
+mod save;
 mod states;
+mod steering;
 
 use std::time::Duration;
 
+use ai::AiPlugin;
 use asset_loader::AssetLoaderPlugin;
+use behavior::BehaviorPlugin;
 use bevy::{
     core_pipeline::experimental::taa::TemporalAntiAliasPlugin,
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
@@ -20,15 +37,28 @@ use bevy_rapier3d::prelude::*;
 //https://github.com/djeedai/bevy_tweening
 use bevy_tweening::*;
 use camera::CameraPlugin;
+use camera_shake::CameraShakePlugin;
+use combat::CombatPlugin;
 use enemy::EnemyPlugin;
+use game_over::GameOverPlugin;
+use graphics::GraphicsPlugin;
+use health::HealthPlugin;
+use loading::LoadingPlugin;
+use main_menu::MainMenuPlugin;
+use menu::MenuPlugin;
 use movable::MovablePlugin;
+use navigation::NavigationPlugin;
+use pause::PausePlugin;
+use perception::PerceptionPlugin;
 use player::PlayerPlugin;
-use states::GameState;
+use save::SavePlugin;
+use states::{GameState, StateCleanupPlugin, StateScoped};
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .add_state::<GameState>()
+        .add_plugins(StateCleanupPlugin)
         .insert_resource(Msaa::Off)
         .insert_resource(ClearColor(Color::rgb(0.1, 0.0, 0.15)))
         .insert_resource(AmbientLight {
@@ -38,15 +68,29 @@ fn main() {
         .add_plugins(EditorPlugin::default())
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
         .add_plugins(RapierDebugRenderPlugin::default())
+        .add_plugins(NavigationPlugin)
         .add_plugins(TweeningPlugin)
         .add_plugins(TemporalAntiAliasPlugin)
         // User Plugins
         .add_plugins(AssetLoaderPlugin)
+        .add_plugins(LoadingPlugin)
         .add_plugins(CameraPlugin)
+        .add_plugins(CameraShakePlugin)
+        .add_plugins(GraphicsPlugin)
         .add_plugins(PlayerPlugin)
         .add_plugins(EnemyPlugin)
+        .add_plugins(PerceptionPlugin)
+        .add_plugins(AiPlugin)
+        .add_plugins(BehaviorPlugin)
         .add_plugins(MovablePlugin)
-        .add_systems(Startup, setup_physics)
+        .add_plugins(HealthPlugin)
+        .add_plugins(CombatPlugin)
+        .add_plugins(GameOverPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(PausePlugin)
+        .add_plugins(MainMenuPlugin)
+        .add_plugins(SavePlugin)
+        .add_systems(OnEnter(GameState::Playing), setup_physics)
         .run();
 }
 
@@ -79,5 +123,6 @@ fn setup_physics(
             }),
             transform: Transform::from_xyz(4.0, 5.0, 0.0),
             ..Default::default()
-        });
+        })
+        .insert(StateScoped(GameState::Playing));
 }
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    asset_loader::{AnimationEntityLink, CharacterAsset, CharacterAssets},
+    character::NameComponent,
+    menu::{menu_root, spawn_menu_button, spawn_menu_title, MenuAction, MenuActionEvent},
+    save::{PendingSave, SaveGame},
+    states::{GameState, StateScoped},
+};
+
+// Idle clip the backdrop character loops once its AnimationPlayer is linked
+#[derive(Component)]
+struct MenuBackdrop(Handle<AnimationClip>);
+
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::MainMenu),
+            (spawn_main_menu, spawn_backdrop),
+        )
+        .add_systems(
+            Update,
+            (main_menu_actions, animate_backdrop).run_if(in_state(GameState::MainMenu)),
+        );
+    }
+}
+
+fn spawn_main_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    // Leave the right half of the screen to the character
+                    width: Val::Percent(50.0),
+                    ..menu_root().style
+                },
+                ..menu_root()
+            },
+            StateScoped(GameState::MainMenu),
+        ))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Main menu");
+            spawn_menu_button(parent, "New Game", MenuAction::NewGame);
+            if SaveGame::exists() {
+                spawn_menu_button(parent, "Continue", MenuAction::Continue);
+            }
+            spawn_menu_button(parent, "Settings", MenuAction::Settings);
+            spawn_menu_button(parent, "Quit", MenuAction::Quit);
+        });
+}
+
+fn spawn_backdrop(
+    mut commands: Commands,
+    character_assets: Res<CharacterAssets>,
+    characters: Res<Assets<CharacterAsset>>,
+) {
+    // Post-processing is added by the graphics plugin like for the gameplay camera
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(-2.0, 2.5, 7.0)
+                .looking_at(Vec3::new(-1.5, 1.5, 0.0), Vec3::Y),
+            ..default()
+        },
+        StateScoped(GameState::MainMenu),
+    ));
+    commands.spawn((
+        PointLightBundle {
+            transform: Transform::from_xyz(2.0, 4.0, 4.0),
+            point_light: PointLight {
+                intensity: 3000.0,
+                color: Color::rgb(0.6, 0.6, 1.0),
+                ..default()
+            },
+            ..default()
+        },
+        StateScoped(GameState::MainMenu),
+    ));
+
+    // The player's character stands behind the menu
+    let Some(character) = character_assets.player(&characters) else {
+        warn!("No player character for the main menu backdrop");
+        return;
+    };
+    let mut backdrop = commands.spawn((
+        SceneBundle {
+            // Models face -forward, towards the camera already
+            scene: character.scene.clone(),
+            ..default()
+        },
+        // Named so its AnimationPlayer gets linked like any other character
+        NameComponent(character.name.clone()),
+        MenuBackdrop(character.animated_movable().idle_animations[0].clone()),
+        StateScoped(GameState::MainMenu),
+    ));
+    if let Some(node) = character.animation_player_node() {
+        backdrop.insert(node);
+    }
+}
+
+fn animate_backdrop(
+    backdrops: Query<(&AnimationEntityLink, &MenuBackdrop), Added<AnimationEntityLink>>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+) {
+    for (link, backdrop) in backdrops.iter() {
+        if let Ok(mut animator) = animation_players.get_mut(link.0) {
+            animator
+                .start_with_transition(backdrop.0.clone_weak(), Duration::from_millis(200))
+                .repeat();
+        }
+    }
+}
+
+fn main_menu_actions(
+    mut commands: Commands,
+    mut action_events: EventReader<MenuActionEvent>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for event in action_events.read() {
+        match event.0 {
+            MenuAction::NewGame => {
+                // A new run replaces the saved one, Continue would bring it back otherwise
+                SaveGame::delete();
+                commands.remove_resource::<PendingSave>();
+                game_state.set(GameState::Playing);
+            }
+            MenuAction::Continue => {
+                if let Some(save) = SaveGame::load() {
+                    commands.insert_resource(PendingSave(save));
+                }
+                game_state.set(GameState::Playing);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::graphics::{GraphicsOption, GraphicsSettings};
+
+const BUTTON_COLOR: Color = Color::rgb(0.2, 0.2, 0.25);
+const BUTTON_HOVER_COLOR: Color = Color::rgb(0.4, 0.3, 0.9);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    NewGame,
+    Continue,
+    Resume,
+    Restart,
+    // Opens the settings over the current menu
+    Settings,
+    // Cycles one graphics option
+    Graphics(GraphicsOption),
+    // Closes the settings
+    Back,
+    Quit,
+}
+
+#[derive(Component)]
+pub struct MenuButton(pub MenuAction);
+
+// Sent when a menu button is pressed, Quit and the settings are handled here,
+// the rest by the menu owning the button
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MenuActionEvent(pub MenuAction);
+
+// Systems that must see a MenuActionEvent before Quit exits the app run in this set,
+// the app exits in the same frame AppExit is sent
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MenuActionSet;
+
+// Text of a Graphics button, shows the option's current value
+#[derive(Component)]
+struct GraphicsLabel(GraphicsOption);
+
+// Column of settings buttons, spawned in place of the buttons of the menu it was opened from
+#[derive(Component)]
+struct SettingsMenu;
+
+// Button selected with the keyboard / gamepad, follows the mouse when it hovers a button
+#[derive(Resource, Debug, Default)]
+struct MenuFocus(Option<Entity>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Navigation {
+    Previous,
+    Next,
+    Activate,
+}
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuFocus>()
+            .add_event::<MenuActionEvent>()
+            .add_systems(
+                Update,
+                (
+                    navigate_menu,
+                    menu_button_colors,
+                    press_menu_buttons.before(MenuActionSet),
+                    handle_menu_actions.after(MenuActionSet),
+                    toggle_settings_menu,
+                    update_graphics_labels,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// Full screen column the buttons of a menu are spawned into
+pub fn menu_root() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(16.0),
+            ..default()
+        },
+        ..default()
+    }
+}
+
+pub fn spawn_menu_title(parent: &mut ChildBuilder, title: &str) {
+    parent.spawn(TextBundle::from_section(
+        title,
+        TextStyle {
+            font_size: 60.0,
+            color: Color::WHITE,
+            ..default()
+        },
+    ));
+}
+
+pub fn spawn_menu_button(parent: &mut ChildBuilder, label: &str, action: MenuAction) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(360.0),
+                    padding: UiRect::axes(Val::Px(24.0), Val::Px(12.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            MenuButton(action),
+        ))
+        .with_children(|button| {
+            let mut text = button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 28.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            if let MenuAction::Graphics(option) = action {
+                text.insert(GraphicsLabel(option));
+            }
+        });
+}
+
+fn navigation_input(
+    keyboard_input: &Input<KeyCode>,
+    gamepads: &Gamepads,
+    gamepad_buttons: &Input<GamepadButton>,
+) -> Option<Navigation> {
+    let gamepad_pressed = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W])
+        || gamepad_pressed(GamepadButtonType::DPadUp)
+    {
+        Some(Navigation::Previous)
+    } else if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S])
+        || gamepad_pressed(GamepadButtonType::DPadDown)
+    {
+        Some(Navigation::Next)
+    } else if keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Space])
+        || gamepad_pressed(GamepadButtonType::South)
+    {
+        Some(Navigation::Activate)
+    } else {
+        None
+    }
+}
+
+// Only one menu is shown at a time, its buttons in the order they were spawned,
+// the buttons of a menu covered by the settings are left out
+fn navigate_menu(
+    mut focus: ResMut<MenuFocus>,
+    all_buttons: Query<(Entity, Ref<Interaction>, &MenuButton, &Parent, &Style)>,
+    children: Query<&Children>,
+    mut action_events: EventWriter<MenuActionEvent>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    let shown = |(.., style): &(_, _, _, _, &Style)| style.display != Display::None;
+    let Some((_, _, _, parent, _)) = all_buttons.iter().find(shown) else {
+        focus.0 = None;
+        return;
+    };
+    let ordered: Vec<Entity> = children
+        .get(parent.get())
+        .map(|children| {
+            children
+                .iter()
+                .copied()
+                .filter(|child| all_buttons.get(*child).is_ok_and(|button| shown(&button)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if let Some((hovered, ..)) = all_buttons
+        .iter()
+        .filter(shown)
+        .find(|(_, interaction, ..)| interaction.is_changed() && **interaction != Interaction::None)
+    {
+        focus.0 = Some(hovered);
+    }
+    let current = focus
+        .0
+        .and_then(|focused| ordered.iter().position(|button| *button == focused));
+
+    let Some(navigation) = navigation_input(&keyboard_input, &gamepads, &gamepad_buttons) else {
+        if current.is_none() {
+            focus.0 = ordered.first().copied();
+        }
+        return;
+    };
+    let count = ordered.len();
+    match (navigation, current) {
+        (Navigation::Previous, Some(index)) => focus.0 = Some(ordered[(index + count - 1) % count]),
+        (Navigation::Next, Some(index)) => focus.0 = Some(ordered[(index + 1) % count]),
+        (Navigation::Activate, Some(index)) => {
+            if let Ok((_, _, button, ..)) = all_buttons.get(ordered[index]) {
+                action_events.send(MenuActionEvent(button.0));
+            }
+        }
+        (_, None) => focus.0 = ordered.first().copied(),
+    }
+}
+
+fn menu_button_colors(
+    focus: Res<MenuFocus>,
+    mut buttons: Query<(Entity, &Interaction, &mut BackgroundColor), With<MenuButton>>,
+) {
+    for (entity, interaction, mut color) in buttons.iter_mut() {
+        let highlighted = *interaction != Interaction::None || focus.0 == Some(entity);
+        *color = if highlighted {
+            BUTTON_HOVER_COLOR.into()
+        } else {
+            BUTTON_COLOR.into()
+        };
+    }
+}
+
+fn press_menu_buttons(
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut action_events: EventWriter<MenuActionEvent>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            action_events.send(MenuActionEvent(button.0));
+        }
+    }
+}
+
+fn handle_menu_actions(
+    mut action_events: EventReader<MenuActionEvent>,
+    mut exit_events: EventWriter<AppExit>,
+    mut settings: ResMut<GraphicsSettings>,
+) {
+    for event in action_events.read() {
+        match event.0 {
+            MenuAction::Quit => exit_events.send(AppExit),
+            MenuAction::Graphics(option) => settings.cycle(option),
+            MenuAction::Settings | MenuAction::Back => {}
+            MenuAction::NewGame
+            | MenuAction::Continue
+            | MenuAction::Resume
+            | MenuAction::Restart => {}
+        }
+    }
+}
+
+// The settings take the place of the menu's own children while open, Back shows them again
+fn toggle_settings_menu(
+    mut commands: Commands,
+    mut action_events: EventReader<MenuActionEvent>,
+    buttons: Query<&Parent, With<MenuButton>>,
+    settings_menus: Query<(Entity, &Parent), With<SettingsMenu>>,
+    children: Query<&Children>,
+    mut styles: Query<&mut Style>,
+) {
+    for event in action_events.read() {
+        match event.0 {
+            MenuAction::Settings => {
+                let Some(menu) = buttons
+                    .iter()
+                    .map(|parent| parent.get())
+                    .find(|menu| !settings_menus.contains(*menu))
+                else {
+                    continue;
+                };
+                for child in children.get(menu).into_iter().flatten() {
+                    if let Ok(mut style) = styles.get_mut(*child) {
+                        style.display = Display::None;
+                    }
+                }
+                commands.entity(menu).with_children(|parent| {
+                    parent
+                        .spawn((menu_root(), SettingsMenu))
+                        .with_children(|parent| {
+                            spawn_menu_title(parent, "Settings");
+                            for option in GraphicsOption::ALL {
+                                spawn_menu_button(parent, "", MenuAction::Graphics(option));
+                            }
+                            spawn_menu_button(parent, "Back", MenuAction::Back);
+                        });
+                });
+            }
+            MenuAction::Back => {
+                for (settings_menu, menu) in settings_menus.iter() {
+                    commands.entity(settings_menu).despawn_recursive();
+                    for child in children.get(menu.get()).into_iter().flatten() {
+                        if let Ok(mut style) = styles.get_mut(*child) {
+                            style.display = Display::Flex;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_graphics_labels(
+    settings: Res<GraphicsSettings>,
+    mut labels: Query<(&mut Text, Ref<GraphicsLabel>)>,
+) {
+    for (mut text, label) in labels.iter_mut() {
+        if settings.is_changed() || label.is_added() {
+            text.sections[0].value = label.0.label(&settings);
+        }
+    }
+}
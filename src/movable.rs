@@ -1,19 +1,128 @@
-use std::time::Duration;
+use std::{
+    f32::consts::{PI, TAU},
+    time::Duration,
+};
 
 use bevy::prelude::*;
 use bevy_rapier3d::control::{KinematicCharacterController, KinematicCharacterControllerOutput};
 
 use crate::{
-    asset_loader::AnimationEntityLink, enemy::EnemyTag, player::PlayerTag, states::GameState,
+    asset_loader::AnimationEntityLink,
+    combat::Attacking,
+    health::{DamageTakenEvent, Dead, DeathEvent, HealthSet, Knockback},
+    states::gameplay_running,
 };
 
-#[derive(Component, Default)]
+// Seconds for a knockback to lose half of its velocity
+const KNOCKBACK_HALF_LIFE: f32 = 0.15;
+// Downward acceleration while airborne, and the speed falling is capped at
+const GRAVITY: f32 = 9.81;
+const TERMINAL_SPEED: f32 = 50.0;
+
+// Locomotion of a character: the player and the AI only set the intent
+// (throttle, turn or heading, fast), `step` turns it into motion on the fixed timestep
+#[derive(Component, Default, Debug, Clone)]
 pub struct Movable {
-    pub acceleration: f32,
-    pub max_acceleration: f32,
+    // -1 full speed backwards, 1 full speed forwards
+    pub throttle: f32,
+    // -1 turning right, 1 turning left
+    pub turn: f32,
+    // Ground direction to turn towards at `turn_rate`, replaces `turn` when set
+    pub heading: Option<Vec3>,
+    // Run instead of walking, only forwards
+    pub fast: bool,
     pub speed: f32,
+    pub vertical_speed: f32,
+    // Running speed, walking is half of it
     pub max_speed: f32,
-    pub fast: bool,
+    // Units per second squared when speeding up / slowing down
+    pub acceleration: f32,
+    pub deceleration: f32,
+    // Radians per second
+    pub turn_rate: f32,
+}
+
+// Motion produced by one locomotion step, in the character's frame
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LocomotionStep {
+    pub forward: f32,
+    pub yaw: f32,
+    pub vertical: f32,
+}
+
+impl Movable {
+    // Speed the throttle asks for, never running backwards
+    pub fn target_speed(&self) -> f32 {
+        let throttle = self.throttle.clamp(-1.0, 1.0);
+        if self.fast && throttle > 0.0 {
+            throttle * self.max_speed
+        } else {
+            throttle * self.max_speed / 2.0
+        }
+    }
+
+    // Advance the locomotion by `delta_seconds` for a character facing `facing`, `locked`
+    // characters (dead, reacting to a hit, attacking) don't walk nor turn but still fall
+    pub fn step(
+        &mut self,
+        facing: Vec3,
+        grounded: bool,
+        locked: bool,
+        delta_seconds: f32,
+    ) -> LocomotionStep {
+        let mut step = LocomotionStep::default();
+
+        if grounded {
+            self.vertical_speed = 0.0;
+        } else {
+            self.vertical_speed =
+                (self.vertical_speed - GRAVITY * delta_seconds).max(-TERMINAL_SPEED);
+            step.vertical = self.vertical_speed * delta_seconds;
+        }
+
+        if locked {
+            self.speed = 0.0;
+            return step;
+        }
+
+        let target = self.target_speed();
+        // Speeding up only while going the same way, anything else is braking
+        let speeding_up = target.abs() > self.speed.abs() && target * self.speed >= 0.0;
+        let rate = if speeding_up {
+            self.acceleration
+        } else {
+            self.deceleration
+        };
+        let max_change = rate * delta_seconds;
+        self.speed += (target - self.speed).clamp(-max_change, max_change);
+
+        step.forward = self.speed * delta_seconds;
+        let max_turn = self.turn_rate * delta_seconds;
+        step.yaw = match self.heading {
+            Some(heading) => yaw_between(facing, heading).clamp(-max_turn, max_turn),
+            None => self.turn.clamp(-1.0, 1.0) * max_turn,
+        };
+        step
+    }
+}
+
+// Character models face their local +Z, which is Bevy's `back()`. Everything walking,
+// aiming or looking along a character goes through these
+pub fn model_forward(transform: &Transform) -> Vec3 {
+    transform.back()
+}
+
+// Rotation around Y of a ground direction, 0 along +Z
+pub fn model_yaw(direction: Vec3) -> f32 {
+    direction.x.atan2(direction.z)
+}
+
+// Signed yaw turning `from` onto `to`, the short way round
+pub fn yaw_between(from: Vec3, to: Vec3) -> f32 {
+    if Vec2::new(to.x, to.z).length_squared() < 1e-6 {
+        return 0.0;
+    }
+    (model_yaw(to) - model_yaw(from) + PI).rem_euclid(TAU) - PI
 }
 
 #[derive(Component)]
@@ -21,100 +130,208 @@ pub struct AnimatedCharacterMovable {
     pub run_animation: Handle<AnimationClip>,
     pub walk_animation: Handle<AnimationClip>,
     pub idle_animations: Vec<Handle<AnimationClip>>,
+    pub take_damage_animation: Option<Handle<AnimationClip>>,
+    pub death_animation: Handle<AnimationClip>,
 }
 
+// Locomotion is interrupted while the take damage clip plays
+#[derive(Component)]
+pub struct HitReaction(pub Timer);
+
+// A clip played on request (by the AI), locomotion animations wait for it to end
+#[derive(Component)]
+pub struct ScriptedAnimation(pub Timer);
+
 pub struct MovablePlugin;
 impl Plugin for MovablePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (move_movables_player, move_movables_enemy, animate_movables)
-                .run_if(in_state(GameState::Playing)),
-        );
+        app.add_systems(FixedUpdate, move_movables.run_if(gameplay_running))
+            .add_systems(
+                Update,
+                (
+                    play_hit_reactions,
+                    play_deaths,
+                    end_hit_reactions,
+                    end_scripted_animations,
+                    animate_movables,
+                )
+                    .chain()
+                    .after(HealthSet)
+                    .run_if(gameplay_running),
+            );
     }
 }
 
-fn move_movables_player(
-    mut movables: Query<
-        (
-            &mut Transform,
-            &mut KinematicCharacterController,
-            &KinematicCharacterControllerOutput,
-            &mut Movable,
-        ),
-        With<PlayerTag>,
-    >,
+// Moves every character through its kinematic controller, player and enemies alike.
+// Runs on the fixed timestep so trajectories don't depend on the frame rate
+fn move_movables(
+    mut movables: Query<(
+        &mut Transform,
+        &mut KinematicCharacterController,
+        &KinematicCharacterControllerOutput,
+        &mut Movable,
+        Option<&mut Knockback>,
+        Has<Dead>,
+        Has<HitReaction>,
+        Has<Attacking>,
+    )>,
     time: Res<Time>,
 ) {
-    for (movable_tranform, mut controller, controller_output, mut movable_data) in
-        movables.iter_mut()
+    let delta_seconds = time.delta_seconds();
+    for (
+        mut transform,
+        mut controller,
+        controller_output,
+        mut movable,
+        knockback,
+        dead,
+        hit_reaction,
+        attacking,
+    ) in movables.iter_mut()
     {
-        if movable_data.fast {
-            movable_data.speed = (movable_data.speed
-                + (movable_data.acceleration * time.delta_seconds()))
-            .clamp(-movable_data.max_speed, movable_data.max_speed);
-        } else {
-            movable_data.speed = (movable_data.speed
-                + (movable_data.acceleration * time.delta_seconds()))
-            .clamp(-movable_data.max_speed / 2.0, movable_data.max_speed / 2.0);
-        }
+        let step = movable.step(
+            model_forward(&transform),
+            controller_output.grounded,
+            dead || hit_reaction || attacking,
+            delta_seconds,
+        );
+        transform.rotate_y(step.yaw);
 
-        if movable_data.speed < 1.0 && movable_data.acceleration.abs() < 9.0 {
-            movable_data.speed = 0.0;
-            movable_data.acceleration = 0.0;
-        } else {
-            let forward = -movable_tranform.forward();
-            let mut move_vector = forward * movable_data.speed * time.delta_seconds();
+        let move_vector = take_knockback(knockback, delta_seconds)
+            + model_forward(&transform) * step.forward
+            + Vec3::Y * step.vertical;
 
-            //movable_tranform.translation += move_vector;
-            if !controller_output.grounded {
-                move_vector += Vec3::new(0.0, -10.0, 0.0) * time.delta_seconds();
-            }
-            controller.translation = Some(move_vector);
+        // Rapier moves the character once per frame, so the steps of a frame add up here while
+        // the rotation above is applied right away, each step walking along its own heading.
+        // They all see the `grounded` of the last rapier move: leaving a ledge or landing is
+        // noticed up to one frame late. That shifts a fall by at most a frame of gravity, and
+        // can't sink the character since rapier resolves the summed translation against the
+        // ground, which the frame rate test with a ledge checks
+        if move_vector != Vec3::ZERO {
+            controller.translation =
+                Some(controller.translation.unwrap_or(Vec3::ZERO) + move_vector);
         }
     }
 }
 
-fn move_movables_enemy(
-    mut movables: Query<(&mut Transform, &mut Movable), With<EnemyTag>>,
+// Knockback displacement for this frame, the velocity fades out exponentially
+fn take_knockback(knockback: Option<Mut<Knockback>>, delta_seconds: f32) -> Vec3 {
+    let Some(mut knockback) = knockback else {
+        return Vec3::ZERO;
+    };
+    let displacement = knockback.0 * delta_seconds;
+    knockback.0 *= 0.5f32.powf(delta_seconds / KNOCKBACK_HALF_LIFE);
+    if knockback.0.length_squared() < 0.01 {
+        knockback.0 = Vec3::ZERO;
+    }
+    displacement
+}
+
+fn play_hit_reactions(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageTakenEvent>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+    targets: Query<(&AnimationEntityLink, &AnimatedCharacterMovable), Without<Dead>>,
+    clips: Res<Assets<AnimationClip>>,
+) {
+    for event in damage_events.read() {
+        let Ok((target, movable_animation)) = targets.get(event.target) else {
+            continue;
+        };
+        let Some(take_damage) = &movable_animation.take_damage_animation else {
+            continue;
+        };
+        let Ok(mut animator) = animation_players.get_mut(target.0) else {
+            continue;
+        };
+
+        // Restart the clip on every hit, even if the previous reaction is still playing
+        animator
+            .start_with_transition(take_damage.clone_weak(), Duration::from_millis(100))
+            .set_speed(1.0);
+        let duration = clips.get(take_damage).map_or(0.5, |clip| clip.duration());
+        commands
+            .entity(event.target)
+            .insert(HitReaction(Timer::from_seconds(duration, TimerMode::Once)))
+            .remove::<ScriptedAnimation>();
+    }
+}
+
+fn play_deaths(
+    mut commands: Commands,
+    mut death_events: EventReader<DeathEvent>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+    mut targets: Query<(
+        &mut Movable,
+        Option<&AnimationEntityLink>,
+        &AnimatedCharacterMovable,
+    )>,
+) {
+    for event in death_events.read() {
+        let Ok((mut movable, target, movable_animation)) = targets.get_mut(event.entity) else {
+            continue;
+        };
+        movable.speed = 0.0;
+        movable.throttle = 0.0;
+        commands
+            .entity(event.entity)
+            .remove::<(HitReaction, ScriptedAnimation)>();
+
+        let Some(mut animator) = target.and_then(|target| animation_players.get_mut(target.0).ok())
+        else {
+            continue;
+        };
+        // Not repeated, the player stays on the last frame once the clip is over
+        animator
+            .start_with_transition(
+                movable_animation.death_animation.clone_weak(),
+                Duration::from_millis(200),
+            )
+            .set_speed(1.0);
+    }
+}
+
+fn end_hit_reactions(
+    mut commands: Commands,
+    mut reactions: Query<(Entity, &mut HitReaction)>,
     time: Res<Time>,
 ) {
-    for (mut movable_tranform, mut movable_data) in movables.iter_mut() {
-        if movable_data.fast {
-            movable_data.speed = (movable_data.speed
-                + (movable_data.acceleration * time.delta_seconds()))
-            .clamp(-movable_data.max_speed, movable_data.max_speed);
-        } else {
-            movable_data.speed = (movable_data.speed
-                + (movable_data.acceleration * time.delta_seconds()))
-            .clamp(-movable_data.max_speed / 2.0, movable_data.max_speed / 2.0);
+    for (entity, mut reaction) in reactions.iter_mut() {
+        if reaction.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<HitReaction>();
         }
+    }
+}
 
-        println!(
-            "Enemy speed: {}, Enemy Acceleration: {}",
-            movable_data.speed, movable_data.acceleration
-        );
-        if movable_data.speed < 1.0 && movable_data.acceleration.abs() < 9.0 {
-            movable_data.speed = 0.0;
-            movable_data.acceleration = 0.0;
-            println!("Enemy stopped")
-        } else {
-            let forward = -movable_tranform.forward();
-            let move_vector = forward * movable_data.speed * time.delta_seconds();
-
-            println!("Move vector: {:?}", move_vector);
-            movable_tranform.translation += move_vector;
+fn end_scripted_animations(
+    mut commands: Commands,
+    mut animations: Query<(Entity, &mut ScriptedAnimation)>,
+    time: Res<Time>,
+) {
+    for (entity, mut animation) in animations.iter_mut() {
+        if animation.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<ScriptedAnimation>();
         }
     }
 }
 
 fn animate_movables(
     mut animation_players: Query<&mut AnimationPlayer>,
-    targets: Query<(&AnimationEntityLink, &Movable, &AnimatedCharacterMovable)>,
+    targets: Query<
+        (&AnimationEntityLink, &Movable, &AnimatedCharacterMovable),
+        (
+            Without<Dead>,
+            Without<HitReaction>,
+            Without<Attacking>,
+            Without<ScriptedAnimation>,
+        ),
+    >,
 ) {
     for (target, movable, movable_animation) in targets.iter() {
-        let mut animator = animation_players.get_mut(target.0).unwrap();
-        if movable.speed == 0.0 && movable.acceleration == 0.0 {
+        let Ok(mut animator) = animation_players.get_mut(target.0) else {
+            continue;
+        };
+        if movable.speed == 0.0 {
             if !animator.is_playing_clip(&movable_animation.idle_animations[0]) {
                 // hack to fix feet position
                 animator
@@ -125,7 +342,7 @@ fn animate_movables(
                     .repeat();
             }
             continue;
-        } else if movable.speed <= movable.max_speed / 2.0 + 0.1 {
+        } else if movable.speed.abs() <= movable.max_speed / 2.0 + 0.1 {
             if !animator.is_playing_clip(&movable_animation.walk_animation) {
                 animator
                     .play_with_transition(
@@ -148,3 +365,226 @@ fn animate_movables(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    fn steve() -> Movable {
+        Movable {
+            max_speed: 14.0,
+            acceleration: 20.0,
+            deceleration: 60.0,
+            turn_rate: 5.0,
+            ..default()
+        }
+    }
+
+    // Runs `move_movables` headless for `seconds` at the given frame rate, applying the
+    // controller translation once per frame like rapier would, on a ground of `ground_height`
+    fn simulate(
+        movable: Movable,
+        start: Vec3,
+        ground_height: fn(Vec3) -> f32,
+        frames_per_second: u32,
+        seconds: f32,
+    ) -> Transform {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(
+                Duration::from_secs(1) / frames_per_second,
+            ))
+            .add_systems(FixedUpdate, move_movables);
+        let entity = app
+            .world
+            .spawn((
+                Transform::from_translation(start),
+                KinematicCharacterController::default(),
+                KinematicCharacterControllerOutput {
+                    grounded: start.y <= ground_height(start),
+                    ..default()
+                },
+                movable,
+            ))
+            .id();
+
+        // The first update only starts the clock
+        let frames = (frames_per_second as f32 * seconds).round() as u32;
+        for _ in 0..=frames {
+            app.update();
+            let mut character = app.world.entity_mut(entity);
+            let translation = character
+                .get_mut::<KinematicCharacterController>()
+                .unwrap()
+                .translation
+                .take();
+            let mut transform = character.get_mut::<Transform>().unwrap();
+            transform.translation += translation.unwrap_or_default();
+            let ground = ground_height(transform.translation);
+            transform.translation.y = transform.translation.y.max(ground);
+            let grounded = transform.translation.y <= ground;
+            character
+                .get_mut::<KinematicCharacterControllerOutput>()
+                .unwrap()
+                .grounded = grounded;
+        }
+        *app.world.get::<Transform>(entity).unwrap()
+    }
+
+    #[test]
+    fn accelerates_to_walking_speed() {
+        let mut movable = Movable {
+            throttle: 1.0,
+            ..steve()
+        };
+        // 7 at 20 per second squared takes 0.35s
+        for _ in 0..20 {
+            movable.step(Vec3::Z, true, false, 1.0 / 64.0);
+        }
+        assert!(movable.speed > 6.0 && movable.speed < 7.0);
+        for _ in 0..20 {
+            movable.step(Vec3::Z, true, false, 1.0 / 64.0);
+        }
+        assert_eq!(movable.speed, 7.0);
+    }
+
+    #[test]
+    fn brakes_to_a_stop_without_reversing() {
+        let mut movable = Movable {
+            speed: 14.0,
+            ..steve()
+        };
+        for _ in 0..64 {
+            movable.step(Vec3::Z, true, false, 1.0 / 64.0);
+        }
+        assert_eq!(movable.speed, 0.0);
+    }
+
+    #[test]
+    fn turns_at_turn_rate() {
+        let mut movable = Movable {
+            turn: -1.0,
+            ..steve()
+        };
+        let yaw: f32 = (0..64)
+            .map(|_| movable.step(Vec3::Z, true, false, 1.0 / 64.0).yaw)
+            .sum();
+        assert!((yaw + 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn turns_towards_heading_without_overshooting() {
+        let mut movable = Movable {
+            heading: Some(Vec3::X),
+            ..steve()
+        };
+        let mut yaw = 0.0;
+        for step in 0..64 {
+            let facing = Quat::from_rotation_y(yaw) * Vec3::Z;
+            yaw += movable.step(facing, true, false, 1.0 / 64.0).yaw;
+            // A quarter turn at 5 radians per second takes about 20 steps
+            if step == 10 {
+                assert!((yaw - 5.0 * 11.0 / 64.0).abs() < 1e-4);
+            }
+        }
+        assert!((yaw - FRAC_PI_2).abs() < 1e-4);
+        assert!(
+            (model_forward(&Transform::from_rotation(Quat::from_rotation_y(yaw))) - Vec3::X)
+                .length()
+                < 1e-3
+        );
+    }
+
+    #[test]
+    fn falling_speed_accumulates_until_grounded() {
+        let mut movable = steve();
+        let fallen: f32 = (0..64)
+            .map(|_| movable.step(Vec3::Z, false, false, 1.0 / 64.0).vertical)
+            .sum();
+        assert!((movable.vertical_speed + GRAVITY).abs() < 1e-3);
+        assert!(fallen < -GRAVITY / 2.0 + 0.1 && fallen > -GRAVITY / 2.0 - 0.2);
+
+        movable.step(Vec3::Z, true, false, 1.0 / 64.0);
+        assert_eq!(movable.vertical_speed, 0.0);
+    }
+
+    #[test]
+    fn locked_characters_stop_but_still_fall() {
+        let mut movable = Movable {
+            throttle: 1.0,
+            turn: 1.0,
+            speed: 7.0,
+            ..steve()
+        };
+        let step = movable.step(Vec3::Z, false, true, 1.0 / 64.0);
+        assert_eq!(movable.speed, 0.0);
+        assert_eq!(step.forward, 0.0);
+        assert_eq!(step.yaw, 0.0);
+        assert!(step.vertical < 0.0);
+    }
+
+    #[test]
+    fn same_trajectory_at_any_frame_rate() {
+        let movable = Movable {
+            throttle: 1.0,
+            turn: 0.1,
+            fast: true,
+            ..steve()
+        };
+        let flat = |_| 0.0;
+        let reference = simulate(movable.clone(), Vec3::ZERO, flat, 64, 2.0);
+        assert!(reference.translation.length() > 10.0);
+        for frames_per_second in [32, 128, 256] {
+            let transform = simulate(movable.clone(), Vec3::ZERO, flat, frames_per_second, 2.0);
+            assert!(
+                transform.translation.distance(reference.translation) < 1e-3,
+                "{frames_per_second} fps ended at {:?} instead of {:?}",
+                transform.translation,
+                reference.translation
+            );
+            assert!(transform.rotation.abs_diff_eq(reference.rotation, 1e-5));
+        }
+    }
+
+    #[test]
+    fn walking_off_a_ledge_lands_the_same_at_any_frame_rate() {
+        let movable = Movable {
+            throttle: 1.0,
+            fast: true,
+            ..steve()
+        };
+        // A one unit step down 2 units ahead, reached after about 0.45s
+        let ledge = |position: Vec3| if position.z < 2.0 { 1.0 } else { 0.0 };
+        let start = Vec3::Y;
+
+        // Halfway through the fall, then once landed
+        let falling = simulate(movable.clone(), start, ledge, 64, 0.75);
+        assert!(falling.translation.y > 0.1 && falling.translation.y < 0.9);
+        let landed = simulate(movable.clone(), start, ledge, 64, 1.5);
+        assert_eq!(landed.translation.y, 0.0);
+
+        for frames_per_second in [32, 128, 256] {
+            // Noticing the ledge a frame late delays the fall by at most that frame
+            let transform = simulate(movable.clone(), start, ledge, frames_per_second, 0.75);
+            let tolerance = GRAVITY * 0.75 / frames_per_second.min(64) as f32;
+            assert!(
+                (transform.translation.y - falling.translation.y).abs() < tolerance,
+                "{frames_per_second} fps was at {} instead of {} while falling",
+                transform.translation.y,
+                falling.translation.y
+            );
+
+            let transform = simulate(movable.clone(), start, ledge, frames_per_second, 1.5);
+            assert!(
+                transform.translation.distance(landed.translation) < 1e-3,
+                "{frames_per_second} fps landed at {:?} instead of {:?}",
+                transform.translation,
+                landed.translation
+            );
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use bevy_rapier3d::geometry::Collider;
+use oxidized_navigation::{query::find_path, NavMesh, NavMeshSettings, OxidizedNavigationPlugin};
+
+use crate::states::gameplay_running;
+
+// Half the size of the floor, nothing walkable lies outside of it
+const WORLD_HALF_EXTENTS: f32 = 50.0;
+// A new path is searched once the destination moved this far from the last one
+const REPATH_DISTANCE: f32 = 1.5;
+// Seconds between two searches for the same enemy, paths are searched on the main thread
+const REPATH_COOLDOWN: f32 = 0.5;
+// Distance on the ground plane at which a waypoint counts as reached
+const WAYPOINT_RADIUS: f32 = 0.5;
+// How far from the navmesh the start and end of a path may be
+const SEARCH_RADIUS: f32 = 2.0;
+
+// Path on the navmesh towards `destination`, set by the AI every frame it moves.
+// Without a path (navmesh still generating, unreachable target) the AI walks straight
+#[derive(Component, Debug)]
+pub struct NavPath {
+    pub destination: Option<Vec3>,
+    // Destination the waypoints were searched for
+    goal: Option<Vec3>,
+    waypoints: Vec<Vec3>,
+    next: usize,
+    cooldown: Timer,
+}
+
+impl Default for NavPath {
+    fn default() -> Self {
+        Self {
+            destination: None,
+            goal: None,
+            waypoints: Vec::new(),
+            next: 0,
+            cooldown: Timer::from_seconds(REPATH_COOLDOWN, TimerMode::Once),
+        }
+    }
+}
+
+impl NavPath {
+    // Point to walk towards to reach `destination` from `position`, the destination
+    // itself when there is no path
+    pub fn steer(&mut self, position: Vec3, destination: Vec3) -> Vec3 {
+        self.destination = Some(destination);
+        while let Some(waypoint) = self.waypoints.get(self.next) {
+            if ground_distance(*waypoint, position) > WAYPOINT_RADIUS {
+                return *waypoint;
+            }
+            self.next += 1;
+        }
+        destination
+    }
+
+    pub fn clear(&mut self) {
+        self.destination = None;
+        self.goal = None;
+        self.waypoints.clear();
+        self.next = 0;
+    }
+
+    fn needs_path(&self, destination: Vec3) -> bool {
+        self.goal
+            .map_or(true, |goal| goal.distance(destination) > REPATH_DISTANCE)
+    }
+}
+
+fn ground_distance(a: Vec3, b: Vec3) -> f32 {
+    Vec2::new(a.x - b.x, a.z - b.z).length()
+}
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        // The navmesh is built from the fixed colliders marked with NavMeshAffector,
+        // sized for the enemy capsules (0.6 radius, about 2 high)
+        app.add_plugins(OxidizedNavigationPlugin::<Collider>::new(NavMeshSettings {
+            cell_width: 0.25,
+            cell_height: 0.1,
+            tile_width: 100,
+            world_half_extents: WORLD_HALF_EXTENTS,
+            world_bottom_bound: -10.0,
+            max_traversable_slope_radians: 40.0_f32.to_radians(),
+            walkable_height: 20,
+            walkable_radius: 3,
+            step_height: 3,
+            min_region_area: 100,
+            merge_region_area: 500,
+            max_contour_simplification_error: 1.1,
+            max_edge_length: 80,
+            max_tile_generation_tasks: Some(9),
+            experimental_detail_mesh_generation: None,
+        }))
+        .add_systems(Update, update_paths.run_if(gameplay_running));
+    }
+}
+
+fn update_paths(
+    mut paths: Query<(&Transform, &mut NavPath)>,
+    nav_mesh: Res<NavMesh>,
+    nav_mesh_settings: Res<NavMeshSettings>,
+    time: Res<Time>,
+) {
+    let nav_mesh = nav_mesh.get();
+    let Ok(tiles) = nav_mesh.read() else {
+        return;
+    };
+    for (transform, mut path) in paths.iter_mut() {
+        path.cooldown.tick(time.delta());
+        let Some(destination) = path.destination else {
+            continue;
+        };
+        if !path.needs_path(destination) || !path.cooldown.finished() {
+            continue;
+        }
+        path.cooldown.reset();
+
+        path.next = 0;
+        match find_path(
+            &tiles,
+            &nav_mesh_settings,
+            transform.translation,
+            destination,
+            Some(SEARCH_RADIUS),
+            None,
+        ) {
+            Ok(waypoints) => {
+                path.goal = Some(destination);
+                path.waypoints = waypoints;
+            }
+            // Walk straight and search again once the cooldown is over
+            Err(error) => {
+                debug!("No path to {destination}: {error:?}");
+                path.goal = None;
+                path.waypoints.clear();
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy_rapier3d::plugin::RapierConfiguration;
+
+use crate::{
+    menu::{menu_root, spawn_menu_button, spawn_menu_title, MenuAction, MenuActionEvent},
+    states::{GameState, PauseState},
+};
+
+#[derive(Component)]
+struct PauseMenu;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<PauseState>()
+            .add_systems(
+                Update,
+                (toggle_pause, resume_from_menu).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                OnEnter(PauseState::Paused),
+                (freeze_gameplay, spawn_pause_menu),
+            )
+            .add_systems(
+                OnExit(PauseState::Paused),
+                (unfreeze_gameplay, despawn_pause_menu),
+            )
+            .add_systems(OnExit(GameState::Playing), resume);
+    }
+}
+
+fn toggle_pause(
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    let pressed = keyboard_input.just_pressed(KeyCode::Escape)
+        || gamepads.iter().any(|gamepad| {
+            gamepad_buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::Start))
+        });
+    if pressed {
+        next_pause_state.set(match pause_state.get() {
+            PauseState::Running => PauseState::Paused,
+            PauseState::Paused => PauseState::Running,
+        });
+    }
+}
+
+fn resume_from_menu(
+    mut action_events: EventReader<MenuActionEvent>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    for event in action_events.read() {
+        if event.0 == MenuAction::Resume {
+            next_pause_state.set(PauseState::Running);
+        }
+    }
+}
+
+// Never stay paused once the run is over
+fn resume(mut next_pause_state: ResMut<NextState<PauseState>>) {
+    next_pause_state.set(PauseState::Running);
+}
+
+// Gameplay systems stop through `gameplay_running`, animations follow virtual time
+fn freeze_gameplay(
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut rapier_configuration: ResMut<RapierConfiguration>,
+) {
+    virtual_time.pause();
+    rapier_configuration.physics_pipeline_active = false;
+}
+
+fn unfreeze_gameplay(
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut rapier_configuration: ResMut<RapierConfiguration>,
+) {
+    virtual_time.unpause();
+    rapier_configuration.physics_pipeline_active = true;
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..menu_root()
+            },
+            PauseMenu,
+        ))
+        .with_children(|parent| {
+            spawn_menu_title(parent, "Paused");
+            spawn_menu_button(parent, "Resume", MenuAction::Resume);
+            spawn_menu_button(parent, "Settings", MenuAction::Settings);
+            spawn_menu_button(parent, "Quit", MenuAction::Quit);
+        });
+}
+
+fn despawn_pause_menu(mut commands: Commands, menus: Query<Entity, With<PauseMenu>>) {
+    for entity in menus.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use bevy_rapier3d::{pipeline::QueryFilter, plugin::RapierContext};
+use serde::Deserialize;
+
+use crate::{
+    enemy::EnemyTag,
+    health::{DamageTakenEvent, Dead, HealthSet},
+    movable::{model_forward, Movable},
+    player::PlayerTag,
+    states::gameplay_running,
+};
+
+// Heights the line of sight is cast between, above the feet
+const EYE_HEIGHT: f32 = 1.5;
+const TARGET_HEIGHT: f32 = 1.0;
+
+// Senses of an enemy, from the `perception` section of its manifest
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct PerceptionStats {
+    pub view_distance: f32,
+    // Full angle of the vision cone, in degrees
+    pub field_of_view: f32,
+    // Footsteps of a walking player are heard this close, through walls
+    pub hearing_radius: f32,
+    // And those of a sprinting one this close
+    pub sprint_hearing_radius: f32,
+    // Seconds the last known position is remembered once the player is out of sight
+    pub memory: f32,
+}
+
+impl Default for PerceptionStats {
+    fn default() -> Self {
+        Self {
+            view_distance: 20.0,
+            field_of_view: 120.0,
+            hearing_radius: 3.0,
+            sprint_hearing_radius: 10.0,
+            memory: 8.0,
+        }
+    }
+}
+
+// What an enemy knows about the player, the AI works from this instead of the player itself
+#[derive(Component, Debug)]
+pub struct Perception {
+    pub stats: PerceptionStats,
+    // The player is in view this frame
+    pub sees_player: bool,
+    // The player was heard this frame, getting hit by it counts
+    pub hears_player: bool,
+    // Where the player was last seen or heard, forgotten after `memory` seconds
+    pub last_known_position: Option<Vec3>,
+    // Velocity of the player when last seen, to lead it
+    pub player_velocity: Vec3,
+    since_last_contact: f32,
+}
+
+impl Perception {
+    pub fn new(stats: PerceptionStats) -> Self {
+        Self {
+            stats,
+            sees_player: false,
+            hears_player: false,
+            last_known_position: None,
+            player_velocity: Vec3::ZERO,
+            since_last_contact: 0.0,
+        }
+    }
+
+    // Position of the player if it is in view
+    pub fn visible_player(&self) -> Option<Vec3> {
+        self.last_known_position.filter(|_| self.sees_player)
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PerceptionSet;
+
+pub struct PerceptionPlugin;
+
+impl Plugin for PerceptionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (perceive, notice_attackers)
+                .chain()
+                .in_set(PerceptionSet)
+                .after(HealthSet)
+                .run_if(gameplay_running),
+        );
+    }
+}
+
+fn perceive(
+    mut enemies: Query<(&Transform, &mut Perception), (With<EnemyTag>, Without<Dead>)>,
+    player: Query<(&Transform, &Movable), (With<PlayerTag>, Without<Dead>, Without<EnemyTag>)>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    let player = player.get_single().ok();
+    for (transform, mut perception) in enemies.iter_mut() {
+        let (sees, hears) = match player {
+            Some((player_transform, player_movable)) => (
+                sees(
+                    &rapier_context,
+                    transform,
+                    player_transform.translation,
+                    &perception.stats,
+                ),
+                hears(
+                    transform.translation,
+                    player_transform.translation,
+                    player_movable,
+                    &perception.stats,
+                ),
+            ),
+            None => (false, false),
+        };
+        perception.sees_player = sees;
+        perception.hears_player = hears;
+
+        perception.player_velocity = match player.filter(|_| sees) {
+            Some((player_transform, player_movable)) => {
+                model_forward(player_transform) * player_movable.speed
+            }
+            None => Vec3::ZERO,
+        };
+        if let Some((player_transform, _)) = player.filter(|_| sees || hears) {
+            perception.last_known_position = Some(player_transform.translation);
+            perception.since_last_contact = 0.0;
+        } else {
+            perception.since_last_contact += time.delta_seconds();
+            if perception.since_last_contact > perception.stats.memory {
+                perception.last_known_position = None;
+            }
+        }
+    }
+}
+
+// Getting hit gives away where the attacker stands, even from behind
+fn notice_attackers(
+    mut damage_events: EventReader<DamageTakenEvent>,
+    mut enemies: Query<&mut Perception, Without<Dead>>,
+    attackers: Query<&Transform, With<PlayerTag>>,
+) {
+    for event in damage_events.read() {
+        let Some(attacker) = event.source.and_then(|source| attackers.get(source).ok()) else {
+            continue;
+        };
+        if let Ok(mut perception) = enemies.get_mut(event.target) {
+            perception.hears_player = true;
+            perception.last_known_position = Some(attacker.translation);
+            perception.since_last_contact = 0.0;
+        }
+    }
+}
+
+// In range, inside the vision cone and not hidden behind level geometry
+fn sees(
+    rapier_context: &RapierContext,
+    transform: &Transform,
+    target: Vec3,
+    stats: &PerceptionStats,
+) -> bool {
+    let to_target = target - transform.translation;
+    let distance = to_target.length();
+    if distance > stats.view_distance {
+        return false;
+    }
+
+    let facing = model_forward(transform);
+    let flat_facing = Vec3::new(facing.x, 0.0, facing.z);
+    let flat_to_target = Vec3::new(to_target.x, 0.0, to_target.z);
+    if flat_to_target.length_squared() > 0.01
+        && flat_facing.angle_between(flat_to_target) > stats.field_of_view.to_radians() / 2.0
+    {
+        return false;
+    }
+
+    // Only fixed bodies block the view, characters don't hide each other
+    let eye = transform.translation + Vec3::Y * EYE_HEIGHT;
+    let ray = target + Vec3::Y * TARGET_HEIGHT - eye;
+    rapier_context
+        .cast_ray(
+            eye,
+            ray.normalize_or_zero(),
+            ray.length(),
+            true,
+            QueryFilter::only_fixed(),
+        )
+        .is_none()
+}
+
+// Standing still makes no noise, sprinting carries much further than walking
+fn hears(position: Vec3, target: Vec3, movable: &Movable, stats: &PerceptionStats) -> bool {
+    if movable.speed == 0.0 {
+        return false;
+    }
+    let radius = if movable.fast {
+        stats.sprint_hearing_radius
+    } else {
+        stats.hearing_radius
+    };
+    position.distance(target) <= radius
+}
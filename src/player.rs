@@ -1,32 +1,37 @@
 use std::time::Duration;
 
-use crate::asset_loader::PlayerSceneAssets;
+use crate::asset_loader::{CharacterAsset, CharacterAssets, LevelAssets};
+use crate::camera_shake::CameraShakeEvent;
 use crate::character::{CharacterPhysicsBody, HealthComponent, NameComponent};
+use crate::combat::{AttackInput, AttackInputEvent};
+use crate::health::{DamageResistances, DamageTakenEvent, Dead, HealthSet};
 use crate::movable::{AnimatedCharacterMovable, Movable};
-use crate::states::GameState;
+use crate::states::{gameplay_running, GameState, StateScoped};
 use bevy::gltf::{Gltf, GltfMesh};
 use bevy::prelude::*;
 
-use bevy_rapier3d::control::{
-    CharacterAutostep, CharacterLength, KinematicCharacterController,
-    KinematicCharacterControllerOutput,
-};
+use bevy_rapier3d::control::KinematicCharacterControllerOutput;
 use bevy_rapier3d::dynamics::Velocity;
 use bevy_rapier3d::geometry::{ComputedColliderShape, Friction};
 use bevy_rapier3d::{
     dynamics::{LockedAxes, RigidBody, Sleeping},
     geometry::{Collider, ColliderMassProperties},
 };
+use oxidized_navigation::NavMeshAffector;
 
 #[derive(Component)]
 pub struct PlayerTag;
 
+// Falling for longer than this shakes the camera on landing, in seconds
+const HEAVY_LANDING_AIR_TIME: f32 = 0.6;
+
 #[derive(Bundle)]
 pub struct PlayerBundle {
     character_physics_body: CharacterPhysicsBody,
     model: SceneBundle,
     name: NameComponent,
     health: HealthComponent,
+    resistances: DamageResistances,
     tag: PlayerTag,
     movable: Movable,
     movable_animation: AnimatedCharacterMovable,
@@ -36,13 +41,24 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Playing), spawn_player_command)
-            .add_systems(Update, move_player.run_if(in_state(GameState::Playing)));
+            .add_systems(
+                Update,
+                (
+                    move_player,
+                    player_attack_input,
+                    shake_on_landing,
+                    shake_on_damage.after(HealthSet),
+                )
+                    .run_if(gameplay_running),
+            );
     }
 }
 
 fn spawn_player_command(
     mut commands: Commands,
-    asset_server: ResMut<PlayerSceneAssets>,
+    character_assets: Res<CharacterAssets>,
+    characters: Res<Assets<CharacterAsset>>,
+    level_assets: Res<LevelAssets>,
     assets_gltf: Res<Assets<Gltf>>,
     asset_gltf_meshes: Res<Assets<GltfMesh>>,
     mut meshes: ResMut<Assets<Mesh>>,                // example plane
@@ -76,6 +92,7 @@ fn spawn_player_command(
             transform: Transform::from_xyz(1.0, 4.0, 0.0).with_scale(Vec3::splat(0.8)),
             ..default()
         })
+        .insert(StateScoped(GameState::Playing))
         .with_children(|children| {
             children.spawn(PointLightBundle {
                 point_light: PointLight {
@@ -89,12 +106,17 @@ fn spawn_player_command(
             });
         });
 
+    let Some(character) = character_assets.player(&characters) else {
+        warn!("No character with the Player spawn role, the player can't be spawned");
+        return;
+    };
+
     let player_mesh = meshes
         .get(
             &asset_gltf_meshes
                 .get(
                     assets_gltf
-                        .get(&asset_server.player_glb)
+                        .get(&character.gltf)
                         .unwrap()
                         .meshes
                         .first()
@@ -109,12 +131,14 @@ fn spawn_player_command(
         )
         .unwrap();
 
-    spawn_player(
-        &mut commands,
-        &asset_server,
-        &player_mesh,
-        "Player".to_string(),
-    );
+    // The player is always alone, only its first spawn point counts
+    if let Some((name, translation)) = character
+        .spawn
+        .as_ref()
+        .and_then(|spawn| spawn.spawn_points(&character.name).into_iter().next())
+    {
+        spawn_player(&mut commands, character, &player_mesh, name, translation);
+    }
 
     //.insert(Collider::capsule_y(0.7, 0.6));
 
@@ -129,7 +153,7 @@ fn spawn_player_command(
         .spawn(PbrBundle {
             mesh: meshes.add(shape::Plane::from_size(100.0).into()),
             material: materials.add(StandardMaterial {
-                base_color_texture: Some(asset_server.chess_texture.clone()),
+                base_color_texture: Some(level_assets.chess_texture.clone()),
                 alpha_mode: AlphaMode::Opaque,
                 perceptual_roughness: 0.08,
                 ..default()
@@ -137,120 +161,186 @@ fn spawn_player_command(
             ..default()
         })
         .insert((Collider::cuboid(50.0, 0.1, 50.0),))
-        .insert(RigidBody::Fixed);
+        .insert(RigidBody::Fixed)
+        .insert(NavMeshAffector)
+        .insert(StateScoped(GameState::Playing));
 
     // test block
     commands
         .spawn(PbrBundle {
             mesh: meshes.add(shape::Box::new(4.0, 4.0, 4.0).into()),
             material: materials.add(StandardMaterial {
-                base_color_texture: Some(asset_server.chess_texture.clone()),
+                base_color_texture: Some(level_assets.chess_texture.clone()),
                 alpha_mode: AlphaMode::Opaque,
                 ..default()
             }),
             transform: Transform::from_xyz(-4.0, 2.0, -4.0),
             ..default()
         })
-        .insert((Collider::cuboid(2.0, 2.0, 2.0), RigidBody::Fixed));
+        .insert((
+            Collider::cuboid(2.0, 2.0, 2.0),
+            RigidBody::Fixed,
+            NavMeshAffector,
+            StateScoped(GameState::Playing),
+        ));
 
     // test block2
     commands
         .spawn(PbrBundle {
             mesh: meshes.add(shape::Box::new(4.0, 1.0, 4.0).into()),
             material: materials.add(StandardMaterial {
-                base_color_texture: Some(asset_server.chess_texture.clone()),
+                base_color_texture: Some(level_assets.chess_texture.clone()),
                 alpha_mode: AlphaMode::Opaque,
                 ..default()
             }),
             transform: Transform::from_xyz(1.0, 0.5, -4.0),
             ..default()
         })
-        .insert((Collider::cuboid(2.0, 0.5, 2.0), RigidBody::Fixed));
+        .insert((
+            Collider::cuboid(2.0, 0.5, 2.0),
+            RigidBody::Fixed,
+            NavMeshAffector,
+            StateScoped(GameState::Playing),
+        ));
 }
 
 fn spawn_player(
     mut commands: &mut Commands,
-    asset_server: &ResMut<PlayerSceneAssets>,
+    character: &CharacterAsset,
     player_mesh: &Mesh,
     name: String,
+    translation: Vec3,
 ) {
-    commands
-        .spawn(PlayerBundle {
-            character_physics_body: CharacterPhysicsBody {
-                rigid_body: RigidBody::KinematicPositionBased,
-                kinematic_controller: KinematicCharacterController {
-                    offset: CharacterLength::Absolute(0.1),
-                    apply_impulse_to_dynamic_bodies: true,
-                    snap_to_ground: Some(CharacterLength::Absolute(10.)),
-                    autostep: Some(CharacterAutostep {
-                        max_height: CharacterLength::Relative(1.),
-                        min_width: CharacterLength::Relative(0.6),
-                        include_dynamic_bodies: false,
-                    }),
-                    ..Default::default()
-                },
-                kinematic_controller_output: KinematicCharacterControllerOutput::default(),
-            },
-            model: SceneBundle {
-                scene: asset_server.player.clone(),
-                transform: Transform::from_translation(Vec3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                }),
-                ..Default::default()
-            },
-            name: NameComponent(name),
-            health: HealthComponent(100.0),
-            tag: PlayerTag,
-            movable: Movable {
-                max_speed: 14.0,
-                max_acceleration: 20.0,
-                ..Default::default()
-            },
-            movable_animation: AnimatedCharacterMovable {
-                run_animation: asset_server.player_run_animation.clone(),
-                walk_animation: asset_server.player_walk_animation.clone(),
-                idle_animations: asset_server.player_idle_animations.clone(),
-            },
-        })
+    let mut player = commands.spawn(PlayerBundle {
+        character_physics_body: CharacterPhysicsBody::default(),
+        model: SceneBundle {
+            scene: character.scene.clone(),
+            transform: Transform::from_translation(translation),
+            ..Default::default()
+        },
+        name: NameComponent(name),
+        health: character.health(),
+        resistances: character.resistances.clone(),
+        tag: PlayerTag,
+        movable: character.movable(),
+        movable_animation: character.animated_movable(),
+    });
+    player
         .insert(Collider::from_bevy_mesh(player_mesh, &ComputedColliderShape::ConvexHull).unwrap())
         // Position the collider relative to the rigid-body.
-        .insert(TransformBundle::from(Transform::from_xyz(0.0, 1.4, 0.0)));
+        .insert(TransformBundle::from(Transform::from_translation(
+            translation + Vec3::Y * 1.4,
+        )))
+        .insert(StateScoped(GameState::Playing));
+
+    if let Some(node) = character.animation_player_node() {
+        player.insert(node);
+    }
+    if let Some(combo) = character.combo() {
+        player.insert(combo);
+    }
 }
 
+// Only sets the intent, the locomotion itself runs on the fixed timestep
 fn move_player(
-    mut player_transforms: Query<(&mut Transform, &mut Movable), With<PlayerTag>>,
-    time: Res<Time>,
+    mut player_movables: Query<&mut Movable, (With<PlayerTag>, Without<Dead>)>,
     keyboard_input: Res<Input<KeyCode>>,
 ) {
-    for (mut player_transform, mut player_movable) in player_transforms.iter_mut() {
-        let rotation = if keyboard_input.pressed(KeyCode::A) {
-            5.0 * time.delta_seconds()
+    for mut player_movable in player_movables.iter_mut() {
+        player_movable.turn = if keyboard_input.pressed(KeyCode::A) {
+            1.0
         } else if keyboard_input.pressed(KeyCode::D) {
-            -5.0 * time.delta_seconds()
+            -1.0
         } else {
             0.0
         };
 
-        let x_movemet = if keyboard_input.pressed(KeyCode::W) {
-            9.0
+        player_movable.throttle = if keyboard_input.pressed(KeyCode::W) {
+            1.0
         } else if keyboard_input.pressed(KeyCode::S) {
-            -9.0
+            -1.0
         } else {
-            -(player_movable.speed * 6.0 + player_movable.acceleration)
+            0.0
         };
 
-        if keyboard_input.pressed(KeyCode::ShiftLeft) {
-            player_movable.fast = true;
-        } else {
-            player_movable.fast = false;
-        }
+        player_movable.fast = keyboard_input.pressed(KeyCode::ShiftLeft);
+    }
+}
 
-        player_movable.acceleration = (player_movable.acceleration + x_movemet).clamp(
-            -player_movable.max_acceleration,
-            player_movable.max_acceleration,
-        );
-        player_transform.rotate_y(rotation);
+// Light: J, left mouse or the west face button, heavy: K or the north face button
+fn player_attack_input(
+    player: Query<Entity, (With<PlayerTag>, Without<Dead>)>,
+    mut attack_events: EventWriter<AttackInputEvent>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let gamepad_pressed = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+
+    let input = if keyboard_input.just_pressed(KeyCode::J)
+        || mouse_buttons.just_pressed(MouseButton::Left)
+        || gamepad_pressed(GamepadButtonType::West)
+    {
+        AttackInput::Light
+    } else if keyboard_input.just_pressed(KeyCode::K) || gamepad_pressed(GamepadButtonType::North) {
+        AttackInput::Heavy
+    } else {
+        return;
+    };
+    attack_events.send(AttackInputEvent {
+        entity: player,
+        input,
+    });
+}
+
+fn shake_on_landing(
+    player: Query<&KinematicCharacterControllerOutput, With<PlayerTag>>,
+    mut shake_events: EventWriter<CameraShakeEvent>,
+    mut air_time: Local<f32>,
+    time: Res<Time>,
+) {
+    let Ok(controller_output) = player.get_single() else {
+        return;
+    };
+
+    if !controller_output.grounded {
+        *air_time += time.delta_seconds();
+        return;
+    }
+
+    if *air_time > HEAVY_LANDING_AIR_TIME {
+        shake_events.send(CameraShakeEvent {
+            trauma: (*air_time * 0.4).min(0.8),
+            origin: None,
+        });
+    }
+    *air_time = 0.0;
+}
+
+fn shake_on_damage(
+    player: Query<(Entity, &HealthComponent), With<PlayerTag>>,
+    mut damage_events: EventReader<DamageTakenEvent>,
+    mut shake_events: EventWriter<CameraShakeEvent>,
+) {
+    let Ok((player, health)) = player.get_single() else {
+        damage_events.clear();
+        return;
+    };
+
+    for event in damage_events.read() {
+        if event.target == player {
+            shake_events.send(CameraShakeEvent {
+                trauma: (event.amount / health.max * 2.0).clamp(0.2, 0.8),
+                origin: None,
+            });
+        }
     }
 }
@@ -0,0 +1,152 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    character::HealthComponent,
+    game_over::RunStats,
+    health::DeathEvent,
+    menu::{MenuAction, MenuActionEvent, MenuActionSet},
+    player::PlayerTag,
+    states::{gameplay_running, GameState},
+};
+
+const SAVE_PATH: &str = "saves/save.ron";
+
+// Progress of a run, written when quitting from the pause menu
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveGame {
+    pub player_position: [f32; 3],
+    // Radians around Y
+    pub player_yaw: f32,
+    pub player_health: f32,
+    pub time_survived: f32,
+    pub enemies_killed: u32,
+}
+
+impl SaveGame {
+    pub fn exists() -> bool {
+        Path::new(SAVE_PATH).exists()
+    }
+
+    pub fn load() -> Option<Self> {
+        let content = fs::read_to_string(SAVE_PATH).ok()?;
+        match ron::from_str(&content) {
+            Ok(save) => Some(save),
+            Err(error) => {
+                warn!("Ignoring invalid {SAVE_PATH}: {error}");
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let content = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Could not serialize save game: {error}");
+                return;
+            }
+        };
+        let path = Path::new(SAVE_PATH);
+        if let Some(folder) = path.parent() {
+            let _ = fs::create_dir_all(folder);
+        }
+        if let Err(error) = fs::write(path, content) {
+            error!("Could not write {SAVE_PATH}: {error}");
+        }
+    }
+
+    pub fn delete() {
+        if Self::exists() {
+            if let Err(error) = fs::remove_file(SAVE_PATH) {
+                error!("Could not delete {SAVE_PATH}: {error}");
+            }
+        }
+    }
+}
+
+// Inserted by Continue, applied to the run once the player is spawned
+#[derive(Resource, Debug)]
+pub struct PendingSave(pub SaveGame);
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                save_on_quit
+                    .in_set(MenuActionSet)
+                    .run_if(in_state(GameState::Playing)),
+                (delete_save_on_death, apply_pending_save).run_if(gameplay_running),
+            ),
+        );
+    }
+}
+
+fn save_on_quit(
+    mut action_events: EventReader<MenuActionEvent>,
+    player: Query<(&Transform, &HealthComponent), With<PlayerTag>>,
+    stats: Res<RunStats>,
+) {
+    if !action_events
+        .read()
+        .any(|event| event.0 == MenuAction::Quit)
+    {
+        return;
+    }
+    let Ok((transform, health)) = player.get_single() else {
+        return;
+    };
+    // A dead player has nothing left to continue
+    if health.current <= 0.0 {
+        return;
+    }
+
+    let (player_yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+    SaveGame {
+        player_position: transform.translation.to_array(),
+        player_yaw,
+        player_health: health.current,
+        time_survived: stats.time_survived,
+        enemies_killed: stats.enemies_killed,
+    }
+    .save();
+}
+
+fn delete_save_on_death(
+    mut death_events: EventReader<DeathEvent>,
+    player: Query<(), With<PlayerTag>>,
+) {
+    if death_events
+        .read()
+        .any(|event| player.contains(event.entity))
+    {
+        SaveGame::delete();
+    }
+}
+
+fn apply_pending_save(
+    mut commands: Commands,
+    pending: Option<Res<PendingSave>>,
+    mut player: Query<(&mut Transform, &mut HealthComponent), With<PlayerTag>>,
+    mut stats: ResMut<RunStats>,
+) {
+    let Some(pending) = pending else {
+        return;
+    };
+    let Ok((mut transform, mut health)) = player.get_single_mut() else {
+        return;
+    };
+
+    let save = &pending.0;
+    transform.translation = Vec3::from_array(save.player_position);
+    transform.rotation = Quat::from_rotation_y(save.player_yaw);
+    health.current = save.player_health.min(health.max);
+    stats.time_survived = save.time_survived;
+    stats.enemies_killed = save.enemies_killed;
+    commands.remove_resource::<PendingSave>();
+}
@@ -2,8 +2,58 @@ use bevy::prelude::*;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum GameState {
+    MainMenu,
     Playing,
     #[default]
     Loading,
+    LoadingError,
     GameOver,
 }
+
+// Only meaningful while in GameState::Playing
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+// Run condition for gameplay systems, playing and not paused
+pub fn gameplay_running(
+    game_state: Res<State<GameState>>,
+    pause_state: Res<State<PauseState>>,
+) -> bool {
+    *game_state.get() == GameState::Playing && *pause_state.get() == PauseState::Running
+}
+
+// Owner state of an entity, it is despawned with its children when that state is left.
+// Only put it on root entities
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct StateScoped(pub GameState);
+
+pub struct StateCleanupPlugin;
+
+impl Plugin for StateCleanupPlugin {
+    fn build(&self, app: &mut App) {
+        // Every GameState variant, new states need to be added here
+        for state in [
+            GameState::MainMenu,
+            GameState::Playing,
+            GameState::Loading,
+            GameState::LoadingError,
+            GameState::GameOver,
+        ] {
+            app.add_systems(OnExit(state.clone()), despawn_state_scoped(state));
+        }
+    }
+}
+
+fn despawn_state_scoped(state: GameState) -> impl FnMut(Commands, Query<(Entity, &StateScoped)>) {
+    move |mut commands, entities| {
+        for (entity, scope) in entities.iter() {
+            if scope.0 == state {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
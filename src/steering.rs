@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+
+use crate::movable::{model_forward, yaw_between, Movable};
+
+// Distance over which `arrive` slows down before its stop distance
+const SLOW_RADIUS: f32 = 3.0;
+// Pursuers lead their target by at most this many seconds
+const MAX_PREDICTION: f32 = 1.0;
+// Wandering aims at a point on a circle ahead, moved a little every frame
+const WANDER_DISTANCE: f32 = 4.0;
+const WANDER_RADIUS: f32 = 2.0;
+// Radians per second the point on the circle moves at most
+const WANDER_JITTER: f32 = 3.0;
+
+// Ground direction a behavior wants to go, and how much of the top speed
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Steering {
+    pub direction: Vec3,
+    pub throttle: f32,
+}
+
+fn ground_direction(vector: Vec3) -> Vec3 {
+    Vec3::new(vector.x, 0.0, vector.z).normalize_or_zero()
+}
+
+pub fn seek(position: Vec3, target: Vec3) -> Steering {
+    Steering {
+        direction: ground_direction(target - position),
+        throttle: 1.0,
+    }
+}
+
+// Like seek, slowing down to stop `stop_distance` away from the target
+pub fn arrive(position: Vec3, target: Vec3, stop_distance: f32) -> Steering {
+    let distance = Vec2::new(target.x - position.x, target.z - position.z).length();
+    Steering {
+        direction: ground_direction(target - position),
+        throttle: ((distance - stop_distance) / SLOW_RADIUS).clamp(0.0, 1.0),
+    }
+}
+
+pub fn flee(position: Vec3, threat: Vec3) -> Steering {
+    Steering {
+        direction: ground_direction(position - threat),
+        throttle: 1.0,
+    }
+}
+
+// Turn towards the target without moving
+pub fn face(position: Vec3, target: Vec3) -> Steering {
+    Steering {
+        direction: ground_direction(target - position),
+        throttle: 0.0,
+    }
+}
+
+// Where to aim to catch a target moving at `target_velocity`, seek or arrive at it
+pub fn pursue(position: Vec3, target: Vec3, target_velocity: Vec3, speed: f32) -> Vec3 {
+    let lead = if speed > 0.0 {
+        (position.distance(target) / speed).min(MAX_PREDICTION)
+    } else {
+        0.0
+    };
+    target + target_velocity * lead
+}
+
+// State of a wandering character, the angle of its point on the wander circle
+#[derive(Debug, Clone, Copy)]
+pub struct Wander {
+    angle: f32,
+    seed: u32,
+}
+
+impl Wander {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            angle: 0.0,
+            // xorshift gets stuck on 0
+            seed: seed.max(1),
+        }
+    }
+
+    // xorshift32, between -1 and 1
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+// Stroll around, heading back once further than `radius` from `home`
+pub fn wander(
+    transform: &Transform,
+    wander: &mut Wander,
+    home: Vec3,
+    radius: f32,
+    delta_seconds: f32,
+) -> Steering {
+    let position = transform.translation;
+    if position.distance(home) > radius {
+        return Steering {
+            throttle: 0.5,
+            ..seek(position, home)
+        };
+    }
+    wander.angle += wander.random() * WANDER_JITTER * delta_seconds;
+    let ahead = position + model_forward(transform) * WANDER_DISTANCE;
+    let target = ahead + Quat::from_rotation_y(wander.angle) * Vec3::Z * WANDER_RADIUS;
+    Steering {
+        throttle: 0.5,
+        ..seek(position, target)
+    }
+}
+
+// Hand a steering over to the locomotion: it turns at the character's turn rate and
+// only moves ahead while roughly facing the direction
+pub fn apply(movable: &mut Movable, transform: &Transform, steering: Steering) {
+    if steering.direction == Vec3::ZERO {
+        movable.heading = None;
+        movable.throttle = 0.0;
+        return;
+    }
+    let alignment = model_forward(transform).dot(steering.direction).max(0.0);
+    movable.heading = Some(steering.direction);
+    movable.throttle = steering.throttle * alignment;
+}
+
+// The model looks at the target within `max_angle` radians
+pub fn is_facing(transform: &Transform, target: Vec3, max_angle: f32) -> bool {
+    yaw_between(model_forward(transform), target - transform.translation).abs() <= max_angle
+}